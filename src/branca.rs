@@ -0,0 +1,78 @@
+// The branche of the group, chosen from a list so that the same branca always gets the same name on
+// the server. Each one can have aliases, other ways of writing it that are recognized in
+// `settings.json` and in the categories of the calendar events. In the settings a branca is either
+// its name or an object with the aliases:
+//
+//     "branche": ["CASTO", { "nome": "LUPI", "alias": ["LUPETTI"] }, ...]
+
+pub const DEFAULT_BRANCHE: [(&str, &[&str]); 5] = [
+    ("CASTO", &["CASTORINI"]),
+    ("LUPI", &["LUPETTI"]),
+    ("ESPLO", &["ESPLORATORI"]),
+    ("PIO", &["PIONIERI"]),
+    ("SEZIONE", &[]),
+];
+
+#[derive(Debug, Clone)]
+pub struct Branca {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+impl Branca {
+    pub fn defaults() -> Vec<Branca> {
+        DEFAULT_BRANCHE.iter()
+            .map(|(name, aliases)| Branca {
+                name: name.to_string(),
+                aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            })
+            .collect()
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.trim();
+        self.name.eq_ignore_ascii_case(text) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(text))
+    }
+
+    /// The branca called `text` (or with `text` among its aliases), ignoring case.
+    pub fn find<'a>(branche: &'a [Branca], text: &str) -> Option<&'a Branca> {
+        branche.iter().find(|branca| branca.matches(text))
+    }
+
+    /// Reads the list of the settings, the error is meant to be shown to the user.
+    pub fn list_from_json(value: &json::JsonValue) -> Result<Vec<Branca>, String> {
+        if !value.is_array() || value.is_empty() {
+            return Err(String::from("deve essere una lista non vuota."));
+        }
+
+        let mut branche: Vec<Branca> = Vec::new();
+        for item in value.members() {
+            let branca = match (item.as_str(), item["nome"].as_str()) {
+                (Some(name), _) => Branca { name: name.trim().to_owned(), aliases: Vec::new() },
+                (None, Some(name)) => {
+                    let aliases = &item["alias"];
+                    let valid = aliases.is_null() || (aliases.is_array() && aliases.members().all(|alias| alias.is_string()));
+                    if !valid {
+                        return Err(format!("gli alias di `{}` devono essere una lista di stringhe.", name));
+                    }
+                    Branca {
+                        name: name.trim().to_owned(),
+                        aliases: aliases.members().filter_map(|alias| alias.as_str()).map(|alias| alias.trim().to_owned()).collect(),
+                    }
+                },
+                _ => return Err(format!("`{}` non è né un nome né un oggetto con il campo \"nome\".", item.dump())),
+            };
+
+            if branca.name.is_empty() {
+                return Err(String::from("il nome di una branca è vuoto."));
+            }
+            let mut names = std::iter::once(&branca.name).chain(branca.aliases.iter());
+            if let Some(duplicate) = names.find(|name| Branca::find(&branche, name).is_some()) {
+                return Err(format!("`{}` compare più di una volta.", duplicate));
+            }
+            branche.push(branca);
+        }
+
+        Ok(branche)
+    }
+}
@@ -0,0 +1,147 @@
+// Scaled previews are kept on disk, so that reopening a saved list does not decode and resize
+// every original again. They are stored as PNG because they are also the images that get
+// uploaded, and must not lose quality.
+//
+// The name of a cached preview is a hash of the path, the modification time and the content of
+// the original, together with the output size: changing any of them simply misses the cache.
+//
+// The cache is in the cache folder of the user (not in the working directory, which changes
+// between runs), and it is trimmed when the application starts: the previews not used for
+// `MAX_AGE_DAYS` are removed, then the least recently used ones until it fits its size. Every use
+// of a preview updates its modification time, which is what tells how recently it was used.
+
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use image::imageops::FilterType::Lanczos3;
+use image::{DynamicImage, ImageFormat, ImageReader};
+
+use crate::exif::Exif;
+
+const CACHE_DIR_NAME: &str = "fototpm";
+const MAX_AGE_DAYS: u64 = 90;
+
+// where the cache folders of the applications go on each system, the temporary folder otherwise
+fn cache_dir() -> PathBuf {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        var("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| home.join(".cache")))
+    };
+    base.unwrap_or_else(env::temp_dir).join(CACHE_DIR_NAME)
+}
+
+// 64 bit FNV-1a, stable across compiler versions (unlike the standard library hasher)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn cache_path(path: &Path, bytes: &[u8], small_dim: u32, big_dim: u32) -> PathBuf {
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    let key = format!("{}\t{}\t{:016x}\t{}x{}", path.display(), mtime, fnv1a(bytes), small_dim, big_dim);
+    cache_dir().join(format!("{:016x}.png", fnv1a(key.as_bytes())))
+}
+
+pub struct Preview {
+    pub image: DynamicImage,
+    pub original_size: (u32, u32),
+    // EXIF data of the original, the preview has none
+    pub exif: Exif,
+    // where the preview can be read again, `None` if it could not be cached
+    pub cached: Option<PathBuf>,
+}
+
+/// Returns the image at `path` resized to fill `small_dim`x`big_dim` (following its orientation).
+pub fn load_preview(path: &Path, small_dim: u32, big_dim: u32) -> Option<Preview> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("[ERROR]: Impossibile leggere `{}`: {}", path.display(), e);
+            return None;
+        },
+    };
+
+    let original_size = ImageReader::new(Cursor::new(&bytes)).with_guessed_format().ok()?.into_dimensions().ok()?;
+    let exif = Exif::read(&bytes);
+    let cached = cache_path(path, &bytes, small_dim, big_dim);
+
+    if let Ok(image) = image::open(&cached) {
+        // marks the preview as just used, for the cleanup
+        let _ = fs::File::options().write(true).open(&cached).and_then(|file| file.set_modified(SystemTime::now()));
+        return Some(Preview { image, original_size, exif, cached: Some(cached) });
+    }
+
+    let img = ImageReader::new(Cursor::new(&bytes)).with_guessed_format().ok()?.decode().ok()?;
+    let image = if original_size.0 > original_size.1 {
+        img.resize_to_fill(big_dim, small_dim, Lanczos3)
+    } else {
+        img.resize_to_fill(small_dim, big_dim, Lanczos3)
+    };
+
+    let saved = fs::create_dir_all(cache_dir())
+        .map_err(image::ImageError::from)
+        .and_then(|_| image.save_with_format(&cached, ImageFormat::Png));
+    let cached = match saved {
+        Ok(_) => Some(cached),
+        Err(e) => {
+            eprintln!("[WARNING]: Impossibile salvare l'anteprima di `{}` nella cache: {}", path.display(), e);
+            None
+        },
+    };
+
+    Some(Preview { image, original_size, exif, cached })
+}
+
+/// Removes the previews not used for a long time, then the least recently used ones until the
+/// cache takes at most `max_bytes`.
+pub fn clean(max_bytes: u64) {
+    let entries = match fs::read_dir(cache_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut previews = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "png"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.path(), metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len()))
+        })
+        .collect::<Vec<_>>();
+    // most recently used first
+    previews.sort_by_key(|(_, used, _)| Reverse(*used));
+
+    let oldest_kept = SystemTime::now() - Duration::from_secs(MAX_AGE_DAYS * 24 * 60 * 60);
+    let mut total = 0;
+    let mut removed = 0;
+    for (path, used, size) in previews {
+        total += size;
+        if used >= oldest_kept && total <= max_bytes {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(_) => removed += 1,
+            Err(e) => eprintln!("[WARNING]: Impossibile rimuovere `{}` dalla cache: {}", path.display(), e),
+        }
+    }
+    if removed > 0 {
+        println!("[INFO]: Removed {} old previews from the cache in `{}`.", removed, cache_dir().display());
+    }
+}
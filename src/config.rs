@@ -0,0 +1,211 @@
+// Optional settings of the graphical application, read from `fototpm-config.json` in the working
+// directory. Every missing field keeps its default value:
+//
+//     {
+//         "memoria_anteprime_mb": 512,
+//         "cache_anteprime_mb": 2048,
+//         "stile_nomi": "CamelCase",
+//         "lunghezza_massima_titolo": 40,
+//         "modello_cartella": "{aa}{mm}{gg}_{BRANCA}_{titolo}",
+//         "modello_file": "{aa}{mm}{gg}_{BRANCA}_{titolo}_{n:3}",
+//         "mese_inizio_anno_scout": 8,
+//         "cartella_anno_scout": "{inizio}-{fine}",
+//         "branche": ["CASTO", { "nome": "LUPI", "alias": ["LUPETTI"] }, "ESPLO", "PIO", "SEZIONE"],
+//         "metadati": true,
+//         "riservatezza": true,
+//         "cartella_private": "private",
+//         "copyright": "Gruppo Scout Roma 1",
+//         "licenza": "CC BY-NC 4.0",
+//         "filigrana": { "logo": "logo.png", "testo": "© Tre Pini 2024" }
+//     }
+//
+// The placeholders of the naming templates are listed in `naming.rs`, the ones of the scouting
+// year folder in `season.rs`; `"cartella_anno_scout": false` disables that folder. The format of
+// the branche is described in `branca.rs`, the metadata written in the saved photos in
+// `metadata.rs`, the watermark in `watermark.rs`. `"cartella_private": false` does not save the
+// private photos at all.
+
+use std::fs;
+
+use crate::branca::Branca;
+use crate::naming::{DEFAULT_FILE_TEMPLATE, DEFAULT_FOLDER_TEMPLATE, FORBIDDEN_CHARS};
+use crate::season::Season;
+use crate::slug::SlugStyle;
+use crate::watermark::Watermark;
+
+const CONFIG_PATH: &str = "fototpm-config.json";
+
+const DEFAULT_PREVIEW_MEMORY_MB: usize = 512;
+const DEFAULT_PREVIEW_CACHE_MB: u64 = 2048;
+const DEFAULT_TITLE_MAX_LEN: usize = 40;
+const DEFAULT_PRIVATE_FOLDER: &str = "private";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    // bytes of preview pixels kept in memory (images and textures), least recently shown ones are
+    // dropped past this point
+    pub preview_memory: usize,
+    // bytes of previews kept in the disk cache, see `cache.rs`
+    pub preview_cache: u64,
+    // how the words of the title are joined in file and folder names
+    pub slug_style: SlugStyle,
+    // characters of the title kept in file and folder names
+    pub title_max_len: usize,
+    // templates of the saved folder and photos, checked when the names are built
+    pub folder_template: String,
+    pub file_template: String,
+    // where the folders of the activities go on the server
+    pub season: Season,
+    // the ones that can be chosen in the `Dati` tab
+    pub branche: Vec<Branca>,
+    // whether title, branca, date, photographer and the rights below go in the saved photos
+    pub write_metadata: bool,
+    // whether camera and position of the original photos are left out, see `metadata.rs`
+    pub privacy: bool,
+    pub copyright: String,
+    pub licence: String,
+    // subfolder of the saved photos where the private ones are saved and never uploaded, `None`
+    // to not save them
+    pub private_folder: Option<String>,
+    // logo and text drawn over the saved photos, `None` without the "filigrana" field
+    pub watermark: Option<Watermark>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            preview_memory: DEFAULT_PREVIEW_MEMORY_MB * 1024 * 1024,
+            preview_cache: DEFAULT_PREVIEW_CACHE_MB * 1024 * 1024,
+            slug_style: SlugStyle::CamelCase,
+            title_max_len: DEFAULT_TITLE_MAX_LEN,
+            folder_template: String::from(DEFAULT_FOLDER_TEMPLATE),
+            file_template: String::from(DEFAULT_FILE_TEMPLATE),
+            season: Season::default(),
+            branche: Branca::defaults(),
+            write_metadata: true,
+            privacy: true,
+            copyright: String::new(),
+            licence: String::new(),
+            private_folder: Some(String::from(DEFAULT_PRIVATE_FOLDER)),
+            watermark: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let mut config = Config::default();
+
+        let content = match fs::read_to_string(CONFIG_PATH) {
+            Ok(content) => content,
+            Err(_) => return config,
+        };
+        let settings = match json::parse(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("[WARNING]: Impossibile leggere `{}`, verranno usate le impostazioni predefinite: {}", CONFIG_PATH, e);
+                return config;
+            },
+        };
+
+        let preview_memory = &settings["memoria_anteprime_mb"];
+        if !preview_memory.is_null() {
+            match preview_memory.as_usize() {
+                Some(mb) => config.preview_memory = mb * 1024 * 1024,
+                None => eprintln!("[WARNING]: Il campo \"memoria_anteprime_mb\" in `{}` deve essere un numero intero.", CONFIG_PATH),
+            }
+        }
+
+        let preview_cache = &settings["cache_anteprime_mb"];
+        if !preview_cache.is_null() {
+            match preview_cache.as_u64() {
+                Some(mb) => config.preview_cache = mb * 1024 * 1024,
+                None => eprintln!("[WARNING]: Il campo \"cache_anteprime_mb\" in `{}` deve essere un numero intero.", CONFIG_PATH),
+            }
+        }
+
+        let slug_style = &settings["stile_nomi"];
+        if !slug_style.is_null() {
+            match slug_style.as_str().and_then(SlugStyle::from_name) {
+                Some(style) => config.slug_style = style,
+                None => eprintln!("[WARNING]: Il campo \"stile_nomi\" in `{}` deve essere \"CamelCase\" oppure \"trattini\".", CONFIG_PATH),
+            }
+        }
+
+        let title_max_len = &settings["lunghezza_massima_titolo"];
+        if !title_max_len.is_null() {
+            match title_max_len.as_usize() {
+                Some(len) if len > 0 => config.title_max_len = len,
+                _ => eprintln!("[WARNING]: Il campo \"lunghezza_massima_titolo\" in `{}` deve essere un numero intero positivo.", CONFIG_PATH),
+            }
+        }
+
+        for (field, template) in [("modello_cartella", &mut config.folder_template), ("modello_file", &mut config.file_template)] {
+            let value = &settings[field];
+            if !value.is_null() {
+                match value.as_str() {
+                    Some(value) => *template = value.to_owned(),
+                    None => eprintln!("[WARNING]: Il campo \"{}\" in `{}` deve essere una stringa.", field, CONFIG_PATH),
+                }
+            }
+        }
+
+        match Season::from_json(&settings) {
+            Ok(season) => config.season = season,
+            Err(e) => eprintln!("[WARNING]: Le impostazioni dell'anno scout in `{}` non sono valide, verranno usate quelle predefinite: {}", CONFIG_PATH, e),
+        }
+
+        let branche = &settings["branche"];
+        if !branche.is_null() {
+            match Branca::list_from_json(branche) {
+                Ok(branche) => config.branche = branche,
+                Err(e) => eprintln!("[WARNING]: Il campo \"branche\" in `{}` non è valido, verranno usate quelle predefinite: {}", CONFIG_PATH, e),
+            }
+        }
+
+        for (field, flag) in [("metadati", &mut config.write_metadata), ("riservatezza", &mut config.privacy)] {
+            let value = &settings[field];
+            if !value.is_null() {
+                match value.as_bool() {
+                    Some(value) => *flag = value,
+                    None => eprintln!("[WARNING]: Il campo \"{}\" in `{}` deve essere `true` oppure `false`.", field, CONFIG_PATH),
+                }
+            }
+        }
+        let private_folder = &settings["cartella_private"];
+        if !private_folder.is_null() {
+            match (private_folder.as_bool(), private_folder.as_str().map(str::trim)) {
+                (Some(false), _) => config.private_folder = None,
+                (_, Some(folder)) if !folder.is_empty() && !folder.contains(|c: char| FORBIDDEN_CHARS.contains(c) || c.is_control()) => {
+                    config.private_folder = Some(folder.to_owned());
+                },
+                _ => eprintln!("[WARNING]: Il campo \"cartella_private\" in `{}` deve essere un nome di cartella valido oppure `false`.", CONFIG_PATH),
+            }
+        }
+
+        if !config.privacy {
+            println!("[INFO]: Privacy mode disabled, camera and position of the photos are going to be kept.");
+        }
+
+        for (field, text) in [("copyright", &mut config.copyright), ("licenza", &mut config.licence)] {
+            let value = &settings[field];
+            if !value.is_null() {
+                match value.as_str() {
+                    Some(value) => *text = value.trim().to_owned(),
+                    None => eprintln!("[WARNING]: Il campo \"{}\" in `{}` deve essere una stringa.", field, CONFIG_PATH),
+                }
+            }
+        }
+
+        let watermark = &settings["filigrana"];
+        if !watermark.is_null() {
+            match Watermark::from_json(watermark) {
+                Ok(watermark) => config.watermark = Some(watermark),
+                Err(e) => eprintln!("[WARNING]: Il campo \"filigrana\" in `{}` non è valido, le foto verranno salvate senza filigrana: {}", CONFIG_PATH, e),
+            }
+        }
+
+        println!("[INFO]: Settings loaded from `{}`.", CONFIG_PATH);
+        config
+    }
+}
@@ -1,12 +1,19 @@
-use std::{env, fs, io::{self, Cursor, Write}, path::PathBuf, process::exit};
+use std::{env, fs, io::{self, Cursor, Write}, path::{Path, PathBuf}, process::exit};
 
+use chrono::NaiveDate;
 use ftp::FtpStream;
-use image::{imageops::FilterType::Triangle, io::Reader as ImageReader};
+use image::{imageops::FilterType::Triangle, ImageReader};
 use image::GenericImageView;
 
-use crate::{check_images_paths, clean_string, find_files, BIGGER_DIMENSION, SMALLER_DIMENSION};
+use crate::{check_images_paths, find_files, BIGGER_DIMENSION, SMALLER_DIMENSION};
+use crate::branca::Branca;
+use crate::exif::{self, Exif};
+use crate::metadata::{self, Metadata};
+use crate::naming::{Activity, Naming, Photo, DEFAULT_FILE_TEMPLATE, DEFAULT_FOLDER_TEMPLATE};
+use crate::season::Season;
+use crate::slug::{slug, SlugStyle};
+use crate::watermark::Watermark;
 
-#[allow(dead_code)]
 fn check_json_null(name: &str, value: &json::JsonValue) {
     if *value == json::Null {
         eprintln!("[ERROR]: Could not parse field \"{}\" in file `settings.json`.\nAborting.", name);
@@ -14,19 +21,18 @@ fn check_json_null(name: &str, value: &json::JsonValue) {
     }
 }
 
-#[allow(dead_code)]
 fn get_string(settings: &json::JsonValue, key: &str) -> String {
     let jv = &settings[key];
     check_json_null(key, jv);
-    if let json::JsonValue::Short(v) = jv {
-        return v.to_owned().to_string();
+    // strings longer than a few bytes are not `Short`, like the copyright or a long title
+    if let Some(v) = jv.as_str() {
+        return v.to_owned();
 
     }
     eprintln!("[ERROR]: Field \"{}\" in file `settings.json` is supposed to be a string.\nAborting.", key);
     exit(1);
 }
 
-#[allow(dead_code)]
 fn get_array_of_strings(settings: &json::JsonValue, key: &str) -> Result<Vec<String>, ()> {
     let jv = &settings[key];
     check_json_null(key, jv);
@@ -45,22 +51,19 @@ fn get_array_of_strings(settings: &json::JsonValue, key: &str) -> Result<Vec<Str
     Err(())
 }
 
-#[allow(dead_code)]
-fn get_clean_string(settings: &json::JsonValue, key: &str) -> String {
-    return clean_string(get_string(&settings, &key));
+fn get_slug(settings: &json::JsonValue, key: &str) -> String {
+    slug(&get_string(settings, key), SlugStyle::CamelCase, usize::MAX)
 }
 
-#[allow(dead_code)]
-fn get_data(settings: &json::JsonValue) -> (String, u64, u64, u64) {
+fn get_data(settings: &json::JsonValue) -> NaiveDate {
     let jv = &settings["data"];
     check_json_null("data", jv);
     if let json::JsonValue::Object(data) = jv {
         if let json::JsonValue::Number(giorno) = data["giorno"] {
             if let json::JsonValue::Number(mese) = data["mese"] {
                 if let json::JsonValue::Number(anno) = data["anno"] {
-                    let s = format!("{}{:02}{:02}", anno.as_parts().1 % 100, mese.as_parts().1 % 100, giorno.as_parts().1 % 100);
-                    if s.len() == 6 {
-                        return (s, anno.as_parts().1, mese.as_parts().1, giorno.as_parts().1);
+                    if let Some(date) = NaiveDate::from_ymd_opt(anno.as_parts().1 as i32, mese.as_parts().1 as u32, giorno.as_parts().1 as u32) {
+                        return date;
                     }
                 }
             }
@@ -68,11 +71,96 @@ fn get_data(settings: &json::JsonValue) -> (String, u64, u64, u64) {
 
     }
 
-    eprintln!("[ERROR]: Field \"data\" in file `settings.json` is invalid.\nAborting.");
+    eprintln!("[ERROR]: Field \"data\" in file `settings.json` is not an existing date.\nAborting.");
     exit(1);
 }
 
-#[allow(dead_code)]
+fn get_branche(settings: &json::JsonValue) -> Vec<Branca> {
+    let jv = &settings["branche"];
+    if jv.is_null() {
+        return Branca::defaults();
+    }
+    match Branca::list_from_json(jv) {
+        Ok(branche) => branche,
+        Err(e) => {
+            eprintln!("[ERROR]: Field \"branche\" in file `settings.json` is invalid: {}\nAborting.", e);
+            exit(1);
+        },
+    }
+}
+
+// the branca in `settings.json`, which must be one of `branche` or of their aliases
+fn get_branca<'a>(settings: &json::JsonValue, branche: &'a [Branca]) -> &'a Branca {
+    let text = get_string(settings, "branca");
+    match Branca::find(branche, &text) {
+        Some(branca) => branca,
+        None => {
+            let names = branche.iter().map(|branca| branca.name.as_str()).collect::<Vec<_>>().join(", ");
+            eprintln!("[ERROR]: Field \"branca\" in file `settings.json` is `{}`, which is not one of: {}.\nAborting.", text, names);
+            exit(1);
+        },
+    }
+}
+
+// the same templates of the graphical application, see `naming.rs`
+fn get_naming(settings: &json::JsonValue) -> Naming {
+    let template = |key: &str, default: &str| if settings[key].is_null() { default.to_owned() } else { get_string(settings, key) };
+    Naming::new(&template("modello_cartella", DEFAULT_FOLDER_TEMPLATE), &template("modello_file", DEFAULT_FILE_TEMPLATE)).unwrap_or_else(|e| {
+        eprintln!("[ERROR]: The naming templates in file `settings.json` are invalid: {}\nAborting.", e);
+        exit(1);
+    })
+}
+
+fn get_season(settings: &json::JsonValue) -> Season {
+    Season::from_json(settings).unwrap_or_else(|e| {
+        eprintln!("[ERROR]: The scouting year settings in file `settings.json` are invalid: {}\nAborting.", e);
+        exit(1);
+    })
+}
+
+fn get_flag(settings: &json::JsonValue, key: &str, default: bool) -> bool {
+    let jv = &settings[key];
+    if jv.is_null() {
+        return default;
+    }
+    jv.as_bool().unwrap_or_else(|| {
+        eprintln!("[ERROR]: Field \"{}\" in file `settings.json` is supposed to be `true` or `false`.\nAborting.", key);
+        exit(1);
+    })
+}
+
+// metadata written in the saved photos, `None` if disabled with `"metadati": false`
+fn get_metadata(settings: &json::JsonValue, branca: &str, date: NaiveDate) -> Option<Metadata> {
+    if !get_flag(settings, "metadati", true) {
+        return None;
+    }
+
+    let optional_string = |key: &str| if settings[key].is_null() { String::new() } else { get_string(settings, key).trim().to_owned() };
+    Some(Metadata {
+        title: get_string(settings, "titolo").trim().to_owned(),
+        branca: branca.to_owned(),
+        date,
+        copyright: optional_string("copyright"),
+        licence: optional_string("licenza"),
+        ..Metadata::default()
+    })
+}
+
+// logo and text drawn over the saved photos, `None` without the "filigrana" field
+fn get_watermark(settings: &json::JsonValue) -> Option<Watermark> {
+    let jv = &settings["filigrana"];
+    if jv.is_null() {
+        return None;
+    }
+    match Watermark::from_json(jv) {
+        Ok(watermark) => Some(watermark),
+        Err(e) => {
+            eprintln!("[ERROR]: Field \"filigrana\" in file `settings.json` is invalid: {}\nAborting.", e);
+            exit(1);
+        },
+    }
+}
+
 fn find_images() -> Vec<PathBuf> {
     let mut images: Vec<PathBuf> = Vec::new();
     for element in std::path::Path::new(".").read_dir().unwrap() {
@@ -90,7 +178,6 @@ fn find_images() -> Vec<PathBuf> {
 }
 
 
-#[allow(dead_code)]
 fn upload_dir(stream: &mut FtpStream, dir: &str) {
     println!("+ Uploading DIR `{}`...", dir);
     stream.mkdir(&dir).unwrap();
@@ -109,7 +196,6 @@ fn upload_dir(stream: &mut FtpStream, dir: &str) {
     }
 }
 
-#[allow(dead_code)]
 fn yes_no_question(question: &str) -> bool {
     print!("{} [Y]/n ", question);
     io::stdout().flush().unwrap();
@@ -124,8 +210,7 @@ fn yes_no_question(question: &str) -> bool {
     return answer == "Y" || answer == "y" || answer == "yes" || answer == "YES" || answer == "Yes";
 }
 
-#[allow(dead_code)]
-fn console_app() {
+pub fn console_app() {
     println!("--- SETTINGS ---");
 
     print!("+ Searching for configuration file: `settings.json`...");
@@ -141,17 +226,38 @@ fn console_app() {
     let settings = json::parse(&settings_file)
         .expect("[ERROR]: Could not parse `settings.json`.\nAborting.");
 
-    let titolo = get_clean_string(&settings, "titolo");
-    let branca = get_clean_string(&settings, "branca").to_uppercase();
+    let branche = get_branche(&settings);
+    let branca = get_branca(&settings, &branche);
     let server = get_string(&settings, "server");
     let utente = get_string(&settings, "utente");
     let password = get_string(&settings, "password");
 
-    let (data, anno, mese, _) = get_data(&settings);
-    let dir_path = format!("{}_{}_{}", data, branca, titolo);
+    let date = get_data(&settings);
+    let activity = Activity {
+        date,
+        end_date: None,
+        branca: slug(&branca.name, SlugStyle::CamelCase, usize::MAX),
+        title: get_slug(&settings, "titolo"),
+    };
+    let naming = get_naming(&settings);
+    let dir_path = naming.folder(&activity).unwrap_or_else(|e| {
+        eprintln!("[ERROR]: {}\nAborting.", e);
+        exit(1);
+    });
+    let season_dir = get_season(&settings).folder(date);
+    let metadata = get_metadata(&settings, &branca.name, date);
+    let privacy = get_flag(&settings, "riservatezza", true);
+    let watermark = get_watermark(&settings);
+    let remote_path = match &season_dir {
+        Some(season_dir) => format!("{}/{}/{}/", server, season_dir, dir_path),
+        None => format!("{}/{}/", server, dir_path),
+    };
 
     let mut images = if let Ok(files) = get_array_of_strings(&settings, "files") {
         check_images_paths(&files.iter().map(|x| x.as_str()).collect())
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
     } else {
         Vec::new()
     };
@@ -188,39 +294,57 @@ fn console_app() {
             std::io::stdout().flush().unwrap();
 
             let img = ImageReader::open(path).unwrap().decode().unwrap();
+            let exif = Exif::read(&fs::read(path).unwrap());
             let size = img.dimensions();
 
-            let img_scaled;
+            let mut img_scaled;
             if size.0 > size.1 {
                 img_scaled = img.resize_to_fill(BIGGER_DIMENSION, SMALLER_DIMENSION, Triangle);
             } else {
                 img_scaled = img.resize_to_fill(SMALLER_DIMENSION, BIGGER_DIMENSION, Triangle);
             }
+            if let Some(watermark) = &watermark {
+                img_scaled = watermark.apply(&img_scaled, false);
+            }
 
-            let new_name = format!("{}/{}_{}_{}_{:03}.JPG", dir_path, data, branca, titolo, n + 1);
+            let photo = Photo {
+                number: n + 1,
+                original: slug(&path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default(), SlugStyle::CamelCase, usize::MAX),
+                photographer: slug(exif.artist.as_deref().unwrap_or_default(), SlugStyle::CamelCase, usize::MAX),
+            };
+            let new_name = format!("{}/{}", dir_path, naming.file(&activity, &photo));
 
-            img_scaled.save(new_name.clone()).expect("[ERROR]: Could not save image.\nAborting.");
+            let photo_metadata = metadata.as_ref().map(|metadata| metadata.for_photo(&exif, privacy));
+            metadata::save_jpeg(&img_scaled, &new_name, photo_metadata.as_ref()).expect("[ERROR]: Could not save image.\nAborting.");
             println!(" done!\n    -> Saved as `{}`!", new_name);
         }
     } else {
         println!("Ok. The current directory is not going to be processed.");
     }
 
+    // the folder is missing if the photos were not processed and never saved before
+    if privacy && Path::new(&dir_path).is_dir() {
+        print!("+ Checking that the photos in `{}` contain no GPS position...", dir_path);
+        io::stdout().flush().unwrap();
+        for file in find_files(&dir_path) {
+            if exif::has_gps(&fs::read(&file).unwrap_or_default()) {
+                eprintln!("\n[ERROR]: `{}` contains a GPS position, the photos are not going to be uploaded.\nAborting.", file.display());
+                exit(1);
+            }
+        }
+        println!(" done!");
+    }
+
     println!();
-    if yes_no_question(&format!("Upload photos in `{}`?", dir_path)) {
+    if yes_no_question(&format!("Upload photos in `{}` to `{}`?", dir_path, remote_path)) {
         println!();
         println!("--- FTP ---");
 
         let mut ftp_stream = FtpStream::connect(format!("{}:21", server)).unwrap();
         ftp_stream.login(&utente, &password).unwrap();
 
-        if mese < 8 {
-            let dir = format!("{}-{}", anno - 1, anno);
-            ftp_stream.cwd(&dir).unwrap();
-            println!("[FTP]: cd {}/", dir);
-        } else {
-            let dir = format!("{}-{}", anno, anno + 1);
-            ftp_stream.cwd(&dir).unwrap();
+        if let Some(dir) = &season_dir {
+            ftp_stream.cwd(dir).unwrap();
             println!("[FTP]: cd {}/", dir);
         }
 
@@ -0,0 +1,255 @@
+// Minimal reader of the EXIF data embedded in the photos (a TIFF structure), only the few tags
+// used by the application are looked up. The ones written in the saved photos are in `metadata.rs`:
+// camera and position are read only to be kept when the privacy mode is disabled, while the maker
+// notes (binary data of the camera, which can contain its serial number) are never read.
+
+use std::convert::TryInto;
+use std::io::Cursor;
+
+use chrono::NaiveDateTime;
+use image::{ImageDecoder, ImageReader};
+
+use crate::metadata::Entry;
+
+pub const TAG_IMAGE_DESCRIPTION: u16 = 0x010e;
+pub const TAG_MAKE: u16 = 0x010f;
+pub const TAG_MODEL: u16 = 0x0110;
+pub const TAG_ARTIST: u16 = 0x013b;
+pub const TAG_COPYRIGHT: u16 = 0x8298;
+pub const TAG_EXIF_IFD: u16 = 0x8769;
+pub const TAG_GPS_IFD: u16 = 0x8825;
+pub const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+pub const TAG_BODY_SERIAL_NUMBER: u16 = 0xa431;
+
+pub const TYPE_ASCII: u16 = 2;
+pub const TYPE_LONG: u16 = 4;
+
+pub const DATE_TIME_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+
+// prefix of the position properties in XMP
+const XMP_GPS: &[u8] = b"exif:GPS";
+
+#[derive(Debug, Clone, Default)]
+pub struct Exif {
+    pub artist: Option<String>,
+    // when the photo was taken, as set in the camera (so without time zone)
+    pub taken: Option<NaiveDateTime>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    // the whole GPS directory, in the form it is written back
+    pub gps: Vec<Entry>,
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    // the structure and the offset of its first directory
+    fn new(chunk: &[u8]) -> Option<(Tiff<'_>, usize)> {
+        let little_endian = match chunk.get(..4)? {
+            [0x49, 0x49, 42, 0] => true,
+            [0x4d, 0x4d, 0, 42] => false,
+            _ => return None,
+        };
+        let tiff = Tiff { data: chunk, little_endian };
+        let ifd0 = tiff.u32(4)? as usize;
+        Some((tiff, ifd0))
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = [*self.data.get(offset)?, *self.data.get(offset + 1)?];
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    // offset of the 12 bytes entry of `tag` in the directory at `ifd`
+    fn find(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let count = self.u16(ifd)? as usize;
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|entry| self.u16(*entry) == Some(tag))
+    }
+
+    fn ascii(&self, entry: usize) -> Option<String> {
+        if self.u16(entry + 2)? != TYPE_ASCII {
+            return None;
+        }
+        let count = self.u32(entry + 4)? as usize;
+        let start = if count <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_owned())
+    }
+
+    // offset of the directory pointed by the entry of `tag` in the directory at `ifd`
+    fn sub_ifd(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let entry = self.find(ifd, tag)?;
+        if self.u16(entry + 2)? != TYPE_LONG {
+            return None;
+        }
+        Some(self.u32(entry + 8)? as usize)
+    }
+
+    fn date_time(&self, ifd: usize, tag: u16) -> Option<NaiveDateTime> {
+        let text = self.ascii(self.find(ifd, tag)?)?;
+        NaiveDateTime::parse_from_str(&text, DATE_TIME_FORMAT).ok()
+    }
+
+    fn text(&self, ifd: usize, tag: u16) -> Option<String> {
+        self.ascii(self.find(ifd, tag)?)
+    }
+
+    // the entry at `entry` with its value, turned little endian
+    fn entry(&self, entry: usize) -> Option<Entry> {
+        let kind = self.u16(entry + 2)?;
+        let count = self.u32(entry + 4)?;
+        // bytes of each number of the value, and numbers of each item
+        let (size, numbers) = match kind {
+            1 | 2 | 6 | 7 => (1, 1),
+            3 | 8 => (2, 1),
+            4 | 9 | 11 => (4, 1),
+            5 | 10 => (4, 2),
+            12 => (8, 1),
+            _ => return None,
+        };
+        let len = (count as usize).checked_mul(size * numbers)?;
+        let start = if len <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+        let data = if self.little_endian {
+            bytes.to_vec()
+        } else {
+            bytes.chunks(size).flat_map(|number| number.iter().rev()).copied().collect()
+        };
+        Some(Entry { tag: self.u16(entry)?, kind, count, data })
+    }
+
+    // the entries of the directory at `ifd`, the ones that cannot be read are skipped
+    fn entries(&self, ifd: usize) -> Vec<Entry> {
+        let count = self.u16(ifd).unwrap_or(0) as usize;
+        (0..count).filter_map(|i| self.entry(ifd + 2 + i * 12)).collect()
+    }
+}
+
+// the EXIF data (TIFF structure) of the image file in `bytes`
+fn exif_chunk(bytes: &[u8]) -> Option<Vec<u8>> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .and_then(|mut decoder| decoder.exif_metadata().ok().flatten())
+}
+
+/// Whether the image file in `bytes` contains a position, in the EXIF data or in XMP.
+pub fn has_gps(bytes: &[u8]) -> bool {
+    let in_exif = exif_chunk(bytes)
+        .and_then(|chunk| Tiff::new(&chunk).map(|(tiff, ifd0)| tiff.find(ifd0, TAG_GPS_IFD).is_some()))
+        .unwrap_or(false);
+    in_exif || bytes.windows(XMP_GPS.len()).any(|window| window == XMP_GPS)
+}
+
+impl Exif {
+    /// Reads the EXIF data of the image file in `bytes`, missing tags are left empty.
+    pub fn read(bytes: &[u8]) -> Exif {
+        exif_chunk(bytes)
+            .map(|chunk| Exif::parse(&chunk))
+            .unwrap_or_default()
+    }
+
+    fn parse(chunk: &[u8]) -> Exif {
+        let (tiff, ifd0) = match Tiff::new(chunk) {
+            Some(tiff) => tiff,
+            None => return Exif::default(),
+        };
+        let exif_ifd = tiff.sub_ifd(ifd0, TAG_EXIF_IFD);
+
+        // the time of the shot is in the EXIF directory; the `DateTime` of the main one is when the
+        // file was last changed, so a photo edited after the activity would seem taken outside of it
+        let taken = exif_ifd.and_then(|exif_ifd| tiff.date_time(exif_ifd, TAG_DATE_TIME_ORIGINAL));
+
+        Exif {
+            artist: tiff.text(ifd0, TAG_ARTIST),
+            taken,
+            make: tiff.text(ifd0, TAG_MAKE),
+            model: tiff.text(ifd0, TAG_MODEL),
+            serial: exif_ifd.and_then(|exif_ifd| tiff.text(exif_ifd, TAG_BODY_SERIAL_NUMBER)),
+            gps: tiff.sub_ifd(ifd0, TAG_GPS_IFD).map(|gps_ifd| tiff.entries(gps_ifd)).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // big endian TIFF with an artist, the time of the shot and a latitude of 41° 53' 0"
+    fn big_endian() -> Vec<u8> {
+        let mut tiff = vec![0x4d, 0x4d, 0, 42, 0, 0, 0, 8];
+        let entry = |tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            tiff.extend(tag.to_be_bytes());
+            tiff.extend(kind.to_be_bytes());
+            tiff.extend(count.to_be_bytes());
+            tiff.extend(value.to_be_bytes());
+        };
+        tiff.extend(3u16.to_be_bytes());
+        entry(&mut tiff, TAG_ARTIST, TYPE_ASCII, 6, 50);
+        entry(&mut tiff, TAG_EXIF_IFD, TYPE_LONG, 1, 56);
+        entry(&mut tiff, TAG_GPS_IFD, TYPE_LONG, 1, 94);
+        tiff.extend(0u32.to_be_bytes());
+        tiff.extend(b"Mario\0");
+        tiff.extend(1u16.to_be_bytes());
+        entry(&mut tiff, TAG_DATE_TIME_ORIGINAL, TYPE_ASCII, 20, 74);
+        tiff.extend(0u32.to_be_bytes());
+        tiff.extend(b"2024:08:01 10:30:00\0");
+        tiff.extend(1u16.to_be_bytes());
+        entry(&mut tiff, 0x0002, 5, 3, 112);
+        tiff.extend(0u32.to_be_bytes());
+        for number in [41, 1, 53, 1, 0, 1u32] {
+            tiff.extend(number.to_be_bytes());
+        }
+        tiff
+    }
+
+    #[test]
+    fn big_endian_tags() {
+        let exif = Exif::parse(&big_endian());
+        assert_eq!(exif.artist.as_deref(), Some("Mario"));
+        assert_eq!(exif.taken, NaiveDateTime::parse_from_str("2024:08:01 10:30:00", DATE_TIME_FORMAT).ok());
+        assert_eq!(exif.gps.len(), 1);
+        let latitude = &exif.gps[0];
+        assert_eq!((latitude.tag, latitude.kind, latitude.count), (0x0002, 5, 3));
+        // turned little endian, as it is written back
+        assert_eq!(&latitude.data[..8], &[41, 0, 0, 0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn truncated_data() {
+        let tiff = big_endian();
+        for len in 0..tiff.len() {
+            let exif = Exif::parse(&tiff[..len]);
+            if len < 50 {
+                assert!(exif.artist.is_none() && exif.taken.is_none() && exif.gps.is_empty());
+            }
+        }
+        assert!(Exif::parse(&[]).artist.is_none());
+        assert!(Exif::parse(b"not a tiff structure").gps.is_empty());
+    }
+
+    #[test]
+    fn offsets_out_of_the_data() {
+        let mut tiff = big_endian();
+        // GPS directory pointed past the end, and a directory claiming far more entries than it has
+        tiff[42..46].copy_from_slice(&u32::MAX.to_be_bytes());
+        tiff[56..58].copy_from_slice(&u16::MAX.to_be_bytes());
+        let exif = Exif::parse(&tiff);
+        assert_eq!(exif.artist.as_deref(), Some("Mario"));
+        assert!(exif.gps.is_empty());
+    }
+}
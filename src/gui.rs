@@ -1,9 +1,18 @@
-use std::ffi::CStr;
+use std::ffi::CString;
 
+use chrono::{Datelike, Local, NaiveDate};
 use ffi::IsKeyPressedRepeat;
 use raylib::ffi::CheckCollisionPointRec;
 use raylib::prelude::*;
 
+// the default raylib font only has ASCII characters, text typed by the user is drawn with this one
+// (and so is the text of the watermark)
+pub const UI_FONT_DATA: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+const UI_FONT_BASE_SIZE: i32 = 48;
+// everything written in Italian (and most western languages): ASCII, Latin-1, Latin Extended-A
+// and typographic punctuation
+const UI_FONT_EXTRA_CHARS: &str = "–—‘’‚“”„…•€";
+
 pub fn is_key_pressed_repeat(key: KeyboardKey) -> bool {
     unsafe { IsKeyPressedRepeat(key as i32) }
 }
@@ -34,129 +43,368 @@ pub fn draw_outlined_text(d: &mut RaylibDrawHandle, text: &str, x: i32, y: i32,
     d.draw_text(text, x, y, font_size, color);
 }
 
-pub fn gui_text_input_update(rl: &mut RaylibHandle, idx: &mut i32, active_index: &mut i32, buffer: &mut Vec<u8>, max_len: usize, text_box: Rectangle) {
+// bounds of a field of the `Dati` tab and the font of its label and text
+#[derive(Clone, Copy)]
+pub struct Field<'a> {
+    pub font: &'a UiFont,
+    pub size: i32,
+    pub rect: Rectangle,
+}
 
-    let mouse_pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
-    let mouse_in_boundaries = unsafe { CheckCollisionPointRec(rl.get_mouse_position().into(), text_box.into()) };
-    if mouse_pressed {
-        if mouse_in_boundaries {
-            *active_index = *idx;
-        } else if *active_index == *idx {
-            *active_index = -1;
+pub struct UiFont {
+    // `None` if the font could not be loaded, the default one is used instead
+    font: Option<Font>,
+}
+
+impl UiFont {
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread) -> UiFont {
+        let chars = (' '..='~')
+            .chain('\u{a0}'..='\u{17f}')
+            .chain(UI_FONT_EXTRA_CHARS.chars())
+            .collect::<String>();
+
+        match rl.load_font_from_memory(thread, ".ttf", UI_FONT_DATA, UI_FONT_BASE_SIZE, Some(&chars)) {
+            Ok(font) => {
+                unsafe { ffi::SetTextureFilter(font.texture, TextureFilter::TEXTURE_FILTER_BILINEAR as i32) };
+                UiFont { font: Some(font) }
+            },
+            Err(e) => {
+                eprintln!("[ERROR]: Impossibile caricare il font, le lettere accentate non verranno mostrate: {}", e);
+                UiFont { font: None }
+            },
         }
     }
-    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
-        *active_index = -1;
+
+    fn spacing(size: i32) -> f32 {
+        size as f32 / 20.0
     }
 
-    if idx == active_index {
-        // update text
-        // rl.set_mouse_cursor(MouseCursor::MOUSE_CURSOR_IBEAM);
-        
-        while let Some(c) = rl.get_char_pressed() {
-            let c = c as u8;
-            // println!("`{}`", c);
-            if (c >= 32) && (c <= 125) && (buffer.len() < max_len) {
-                // println!("{:?}", buffer);
-                buffer.push(c);
-                // println!("add char {:?}", buffer);
-            }
+    pub fn measure(&self, text: &str, size: i32) -> i32 {
+        let text = CString::new(text).unwrap_or_default();
+        match &self.font {
+            Some(font) => unsafe { ffi::MeasureTextEx(**font, text.as_ptr(), size as f32, UiFont::spacing(size)).x as i32 },
+            None => unsafe { ffi::MeasureText(text.as_ptr(), size) },
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, text: &str, x: i32, y: i32, size: i32, color: Color) {
+        match &self.font {
+            Some(font) => d.draw_text_ex(font, text, rvec2(x, y), size as f32, UiFont::spacing(size), color),
+            None => d.draw_text(text, x, y, size, color),
         }
+    }
+}
 
-        if check_ctrl_shortcut(rl, Some(KeyboardKey::KEY_V)) {
-            if let Ok(x) = rl.get_clipboard_text() {
-                for c in x.chars() {
-                    let c = c as u8;
-                    // println!("`{}`", c);
-                    if (c >= 32) && (c <= 125) && (buffer.len() < max_len) {
-                        // println!("{:?}", buffer);
-                        buffer.push(c);
-                        // println!("add char {:?}", buffer);
-                    }
-                }
+// content of a single line text field, together with its caret and selection
+#[derive(Debug, Clone)]
+pub struct TextInput {
+    text: String,
+    max_len: usize,
+    // characters that can be typed
+    accepts: fn(char) -> bool,
+    // positions are counted in characters: the selection goes from `anchor` to `caret` and is
+    // empty when they are equal
+    caret: usize,
+    anchor: usize,
+    // first character shown when the text does not fit in the box
+    scroll: usize,
+    // the mouse was pressed inside the box and is still down
+    dragging: bool,
+    // the text is never shown nor copied
+    secret: bool,
+}
+
+impl TextInput {
+    pub fn new(max_len: usize) -> TextInput {
+        TextInput {
+            text: String::new(),
+            max_len,
+            accepts: |c| !c.is_control(),
+            caret: 0,
+            anchor: 0,
+            scroll: 0,
+            dragging: false,
+            secret: false,
+        }
+    }
+
+    pub fn filtered(max_len: usize, accepts: fn(char) -> bool) -> TextInput {
+        TextInput { accepts, ..TextInput::new(max_len) }
+    }
+
+    pub fn secret(max_len: usize) -> TextInput {
+        TextInput { secret: true, ..TextInput::new(max_len) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().filter(|c| (self.accepts)(*c)).take(self.max_len).collect();
+        self.set_caret(self.len(), false);
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_offset(&self, pos: usize) -> usize {
+        self.text.char_indices().nth(pos).map(|(i, _)| i).unwrap_or(self.text.len())
+    }
+
+    // the text as it is drawn
+    fn shown(&self) -> String {
+        if self.secret {
+            "*".repeat(self.len())
+        } else {
+            self.text.clone()
+        }
+    }
+
+    fn selection(&self) -> (usize, usize) {
+        (self.caret.min(self.anchor), self.caret.max(self.anchor))
+    }
+
+    fn selected_text(&self) -> &str {
+        let (start, end) = self.selection();
+        &self.text[self.byte_offset(start)..self.byte_offset(end)]
+    }
+
+    // puts the selected text in the clipboard, returns whether something was copied
+    fn copy_selection(&self, rl: &mut RaylibHandle) -> bool {
+        let (start, end) = self.selection();
+        if self.secret || start == end {
+            return false;
+        }
+        match rl.set_clipboard_text(self.selected_text()) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("[ERROR]: Impossibile copiare il testo: {}", e);
+                false
+            },
+        }
+    }
+
+    fn set_caret(&mut self, pos: usize, extend_selection: bool) {
+        self.caret = pos.min(self.len());
+        if !extend_selection {
+            self.anchor = self.caret;
+        }
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let (start_byte, end_byte) = (self.byte_offset(start), self.byte_offset(end));
+        self.text.replace_range(start_byte..end_byte, "");
+        self.set_caret(start, false);
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let (start, end) = self.selection();
+        self.delete_range(start, end);
+        start != end
+    }
+
+    // replaces the selection with the accepted characters of `s`, as long as they fit
+    fn insert(&mut self, s: &str) {
+        self.delete_selection();
+        let accepted = s.chars().filter(|c| (self.accepts)(*c)).collect::<Vec<_>>();
+        for c in accepted {
+            if self.len() >= self.max_len {
+                break;
             }
+            let byte = self.byte_offset(self.caret);
+            self.text.insert(byte, c);
+            self.set_caret(self.caret + 1, false);
         }
+    }
 
-        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE) {
-            // println!("{:?}", buffer);
-            if check_ctrl_shortcut(rl, None) {
-                while buffer.last().unwrap_or(&(' ' as u8)).to_owned() != ' ' as u8 {
-                    buffer.pop();
-                }
+    // start of the word before `pos`, skipping the spaces in between
+    fn word_start(&self, pos: usize) -> usize {
+        if self.secret {
+            return 0;
+        }
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut pos = pos;
+        while pos > 0 && chars[pos - 1] == ' ' {
+            pos -= 1;
+        }
+        while pos > 0 && chars[pos - 1] != ' ' {
+            pos -= 1;
+        }
+        pos
+    }
+
+    // end of the word after `pos`, skipping the spaces in between
+    fn word_end(&self, pos: usize) -> usize {
+        if self.secret {
+            return self.len();
+        }
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut pos = pos;
+        while pos < chars.len() && chars[pos] == ' ' {
+            pos += 1;
+        }
+        while pos < chars.len() && chars[pos] != ' ' {
+            pos += 1;
+        }
+        pos
+    }
+
+    // position of the caret closest to `x` pixels from the left side of the shown text
+    fn position_at(&self, font: &UiFont, x: f32, font_size: i32) -> usize {
+        let shown = self.shown().chars().skip(self.scroll).collect::<String>();
+        let mut best = (self.scroll, x.abs());
+        for (i, (byte, c)) in shown.char_indices().enumerate() {
+            let width = font.measure(&shown[..byte + c.len_utf8()], font_size) as f32;
+            if (width - x).abs() < best.1 {
+                best = (self.scroll + i + 1, (width - x).abs());
             }
-            buffer.pop();
-            // println!("delete   {:?}", buffer);
-            // println!("BACK!");
         }
+        best.0
     }
+}
 
-    *idx += 1;
+fn is_key_typed(rl: &RaylibHandle, key: KeyboardKey) -> bool {
+    rl.is_key_pressed(key) || is_key_pressed_repeat(key)
 }
 
-pub fn gui_number_input_update(rl: &mut RaylibHandle, idx: &mut i32, active_index: &mut i32, buffer: &mut Vec<u8>, max_len: usize, text_box: Rectangle) {
+fn text_input_padding(font_size: i32) -> i32 {
+    font_size * 2 / 3
+}
+
+pub fn gui_text_input_update(rl: &mut RaylibHandle, idx: &mut i32, active_index: &mut i32, input: &mut TextInput, font: &UiFont, font_size: i32, text_box: Rectangle) {
+    let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+    let mouse_x = rl.get_mouse_position().x - text_box.x - text_input_padding(font_size) as f32;
+
     let mouse_pressed = rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
     let mouse_in_boundaries = unsafe { CheckCollisionPointRec(rl.get_mouse_position().into(), text_box.into()) };
     if mouse_pressed {
         if mouse_in_boundaries {
+            let extend_selection = shift && *active_index == *idx;
             *active_index = *idx;
+            input.set_caret(input.position_at(font, mouse_x, font_size), extend_selection);
+            input.dragging = true;
         } else if *active_index == *idx {
             *active_index = -1;
         }
     }
+    if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+        input.dragging = false;
+    }
     if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
         *active_index = -1;
     }
 
-    if idx == active_index {
-        // update text
-        // rl.set_mouse_cursor(MouseCursor::MOUSE_CURSOR_IBEAM);
-        
+    if *idx == *active_index {
+        if input.dragging && !mouse_pressed {
+            input.set_caret(input.position_at(font, mouse_x, font_size), true);
+        }
+
+        let control = check_ctrl_shortcut(rl, None);
+
+        if check_ctrl_shortcut(rl, Some(KeyboardKey::KEY_A)) {
+            input.anchor = 0;
+            input.caret = input.len();
+        } else if check_ctrl_shortcut(rl, Some(KeyboardKey::KEY_C)) {
+            input.copy_selection(rl);
+        } else if check_ctrl_shortcut(rl, Some(KeyboardKey::KEY_X)) {
+            if input.copy_selection(rl) {
+                input.delete_selection();
+            }
+        } else if check_ctrl_shortcut(rl, Some(KeyboardKey::KEY_V)) {
+            if let Ok(x) = rl.get_clipboard_text() {
+                input.insert(&x);
+            }
+        }
+
         while let Some(c) = rl.get_char_pressed() {
-            let c = c as u8;
-            // println!("`{}`", c);
-            if (c >= 48) && (c <= 57) && (buffer.len() < max_len) {
-                buffer.push(c);
+            if !control {
+                input.insert(c.encode_utf8(&mut [0; 4]));
+            }
+        }
+
+        if is_key_typed(rl, KeyboardKey::KEY_LEFT) {
+            let (start, end) = input.selection();
+            if control {
+                input.set_caret(input.word_start(input.caret), shift);
+            } else if start != end && !shift {
+                input.set_caret(start, false);
+            } else {
+                input.set_caret(input.caret.saturating_sub(1), shift);
+            }
+        }
+        if is_key_typed(rl, KeyboardKey::KEY_RIGHT) {
+            let (start, end) = input.selection();
+            if control {
+                input.set_caret(input.word_end(input.caret), shift);
+            } else if start != end && !shift {
+                input.set_caret(end, false);
+            } else {
+                input.set_caret(input.caret + 1, shift);
             }
         }
+        if is_key_typed(rl, KeyboardKey::KEY_HOME) {
+            input.set_caret(0, shift);
+        }
+        if is_key_typed(rl, KeyboardKey::KEY_END) {
+            input.set_caret(input.len(), shift);
+        }
 
-        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE) {
-            buffer.pop();
-            // println!("BACK!");
+        if is_key_typed(rl, KeyboardKey::KEY_BACKSPACE) && !input.delete_selection() {
+            let start = if control { input.word_start(input.caret) } else { input.caret.saturating_sub(1) };
+            input.delete_range(start, input.caret);
+        }
+        if is_key_typed(rl, KeyboardKey::KEY_DELETE) && !input.delete_selection() {
+            let end = if control { input.word_end(input.caret) } else { (input.caret + 1).min(input.len()) };
+            let caret = input.caret;
+            input.delete_range(caret, end);
         }
     }
+
     *idx += 1;
 }
 
-pub fn gui_text_input(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32, label: &str, buffer: &mut Vec<u8>, size: i32, text_box: Rectangle) {
+pub fn gui_text_input(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32, label: &str, input: &mut TextInput, field: Field) {
+    let Field { font, size, rect: text_box } = field;
     let is_active = *idx == active_idx;
-    
-    let mut buf = buffer.clone();
-    buf.push('~' as u8);
-    buf.push(0);
 
-    d.draw_text(label, text_box.x as i32, text_box.y as i32 - size, size, Color::WHITE);
+    font.draw(d, label, text_box.x as i32, text_box.y as i32 - size, size, Color::WHITE);
 
-    let mut str = unsafe { CStr::from_bytes_with_nul_unchecked(&buf).to_str().unwrap_or_default().to_owned() };
-    let mut s = str.clone();
-    let side_padding = size*2/3;
-    
-    while d.measure_text(&s, size) > text_box.width as i32 - side_padding*2 {
-        let mut chars = str.chars();
-        if is_active {
-            chars.next();
-            s = format!("...{}", chars.as_str());
-        } else {
-            chars.next_back();
-            s = format!("{}...", chars.as_str());
+    let side_padding = text_input_padding(size);
+    let max_width = text_box.width as i32 - side_padding*2;
+    let shown = input.shown().chars().collect::<Vec<_>>();
+    let width_of = |chars: &[char]| font.measure(&chars.iter().collect::<String>(), size);
+
+    // keeps the caret in view, showing as much text as possible
+    if is_active {
+        input.scroll = input.scroll.min(input.caret);
+        while input.scroll < input.caret && width_of(&shown[input.scroll..input.caret]) > max_width {
+            input.scroll += 1;
+        }
+        while input.scroll > 0 && width_of(&shown[input.scroll - 1..]) <= max_width {
+            input.scroll -= 1;
         }
-        str = chars.as_str().to_owned();
+    } else {
+        input.scroll = 0;
+    }
+    let mut end = shown.len();
+    while end > input.scroll && width_of(&shown[input.scroll..end]) > max_width {
+        end -= 1;
     }
 
-    s = if is_active && (d.get_time() / 0.5)as u32 % 2 == 0 {
-        s.replace('~', "_")
-    } else {
-        s.replace('~', " ")
-    };
+    let mut s = shown[input.scroll..end].iter().collect::<String>();
+    if !is_active && end < shown.len() {
+        let mut chars = shown[..end].to_vec();
+        while !chars.is_empty() && width_of(&chars) + font.measure("...", size) > max_width {
+            chars.pop();
+        }
+        s = format!("{}...", chars.iter().collect::<String>());
+    }
 
     let (fg, bg, outline_size) = if is_active {
         (Color::new(4, 146, 199, 255), Color::new(151, 232, 255, 255), 1.0)
@@ -166,21 +414,586 @@ pub fn gui_text_input(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32,
         (Color::new(104, 104, 104, 255), Color::WHITE.alpha(0.0), 1.0)
     };
 
+    let text_x = text_box.x as i32 + side_padding;
+    let text_y = text_box.y as i32 + (text_box.height * 0.6) as i32 - size / 2;
+
     // d.draw_rectangle(text_box.x as i32, text_box.y as i32, text_box.width as i32, text_box.height as i32, fg);
     d.draw_rectangle(text_box.x as i32, text_box.y as i32, text_box.width as i32, text_box.height as i32, bg);
     d.draw_line_ex(rvec2(text_box.x, text_box.y), rvec2(text_box.x + text_box.width, text_box.y), outline_size, fg);
     d.draw_line_ex(rvec2(text_box.x + text_box.width, text_box.y), rvec2(text_box.x + text_box.width, text_box.y + text_box.height), outline_size, fg);
     d.draw_line_ex(rvec2(text_box.x + text_box.width, text_box.y + text_box.height), rvec2(text_box.x, text_box.y + text_box.height), outline_size, fg);
     d.draw_line_ex(rvec2(text_box.x, text_box.y + text_box.height), rvec2(text_box.x, text_box.y), outline_size, fg);
-    d.draw_text(&s, text_box.x as i32 + side_padding, text_box.y as i32 + (text_box.height * 0.6) as i32 - size / 2, size, fg);
+
+    if is_active {
+        let (start, end_sel) = input.selection();
+        let (start, end_sel) = (start.clamp(input.scroll, end), end_sel.clamp(input.scroll, end));
+        if start < end_sel {
+            let x0 = width_of(&shown[input.scroll..start]);
+            let x1 = width_of(&shown[input.scroll..end_sel]);
+            d.draw_rectangle(text_x + x0, text_y - size / 8, x1 - x0, size + size / 4, fg.alpha(0.35));
+        }
+    }
+
+    font.draw(d, &s, text_x, text_y, size, fg);
+
+    if is_active && ((d.get_time() / 0.5) as u32).is_multiple_of(2) {
+        let caret_x = text_x + width_of(&shown[input.scroll..input.caret]);
+        d.draw_line_ex(rvec2(caret_x, text_y - size / 8), rvec2(caret_x, text_y + size + size / 8), 2.0, fg);
+    }
     // d.gui_text_box(text_box, &mut buf, *idx == active_idx);
     *idx += 1;
 }
 
-pub fn gui_seecret_text_input(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32, label: &str, buffer: &mut Vec<u8>, size: i32, text_box: Rectangle) {
-    let mut seecret_data = buffer.iter().map(|_| '*' as u8).collect::<Vec<_>>();
+const MONTH_NAMES: [&str; 12] = [
+    "Gennaio", "Febbraio", "Marzo", "Aprile", "Maggio", "Giugno",
+    "Luglio", "Agosto", "Settembre", "Ottobre", "Novembre", "Dicembre",
+];
+const WEEKDAY_INITIALS: [&str; 7] = ["L", "M", "M", "G", "V", "S", "D"];
+// rows of the calendar: month, days of the week, six weeks and the `Oggi` button
+const CALENDAR_ROWS: usize = 9;
+
+/// Reads a date written as `gg/mm/aaaa` (`-` and `.` work as separators too), a two digits year
+/// is taken as 20xx. The error is meant to be shown to the user.
+pub fn parse_date(text: &str) -> Result<NaiveDate, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(String::from("Manca la data dell'attività."));
+    }
+
+    let parts = text.split(['/', '-', '.']).collect::<Vec<_>>();
+    let numbers = parts.iter().map(|p| p.parse::<u32>().ok()).collect::<Option<Vec<_>>>();
+    let (day, month, year) = match (parts.as_slice(), numbers.as_deref()) {
+        ([_, _, y], Some(&[day, month, year])) if y.len() == 4 => (day, month, year),
+        ([_, _, y], Some(&[day, month, year])) if y.len() == 2 => (day, month, 2000 + year),
+        _ => return Err(format!("La data `{}` non è nel formato gg/mm/aaaa.", text)),
+    };
+
+    NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(|| format!("La data `{}` non esiste.", text))
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format("%d/%m/%Y").to_string()
+}
+
+// first day of the month `months` months after the one of `date`
+fn month_start(date: NaiveDate, months: i32) -> NaiveDate {
+    let index = date.year() * 12 + date.month0() as i32 + months;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1).unwrap_or(date)
+}
+
+// day in the cell of the calendar of `month` at the given week row and day of the week
+fn calendar_day(month: NaiveDate, week: usize, weekday: usize) -> NaiveDate {
+    let offset = month.weekday().num_days_from_monday() as i64;
+    month + chrono::Duration::days(week as i64 * 7 + weekday as i64 - offset)
+}
+
+// date field with a button that opens a monthly calendar
+#[derive(Debug, Clone)]
+pub struct DateInput {
+    input: TextInput,
+    // first day of the month shown by the calendar, `None` when it is closed
+    calendar: Option<NaiveDate>,
+}
+
+impl DateInput {
+    pub fn new(date: Option<NaiveDate>) -> DateInput {
+        let mut input = TextInput::filtered(10, |c| c.is_ascii_digit() || "/-.".contains(c));
+        if let Some(date) = date {
+            input.set_text(&format_date(date));
+        }
+        DateInput { input, calendar: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn date(&self) -> Result<NaiveDate, String> {
+        parse_date(self.input.as_str())
+    }
+
+    pub fn set_date(&mut self, date: NaiveDate) {
+        self.input.set_text(&format_date(date));
+    }
+
+    pub fn clear(&mut self) {
+        self.input.set_text("");
+    }
+
+    pub fn is_calendar_open(&self) -> bool {
+        self.calendar.is_some()
+    }
+}
+
+// the text box and, on its right, the square button of the calendar
+fn date_input_rects(rect: Rectangle) -> (Rectangle, Rectangle) {
+    let gap = rect.height / 8.0;
+    let text_box = rrect(rect.x, rect.y, rect.width - rect.height - gap, rect.height);
+    let button = rrect(rect.x + rect.width - rect.height, rect.y, rect.height, rect.height);
+    (text_box, button)
+}
+
+// the calendar goes below the field, or above it when there is no room, and the side of its cells
+fn calendar_layout(rect: Rectangle, font_size: i32, screen_height: i32) -> (Rectangle, f32) {
+    let cell = font_size as f32 * 1.8;
+    let padding = cell / 4.0;
+    let width = cell * 7.0 + padding * 2.0;
+    let height = cell * CALENDAR_ROWS as f32 + padding * 2.0;
+
+    let below = rect.y + rect.height + padding;
+    let y = if below + height <= screen_height as f32 { below } else { (rect.y - height - padding).max(0.0) };
+    (rrect(rect.x, y, width, height), cell)
+}
+
+// row and column of the calendar cell under `point`
+fn calendar_cell(popup: Rectangle, cell: f32, point: Vector2) -> Option<(usize, usize)> {
+    let padding = cell / 4.0;
+    let (x, y) = (point.x - popup.x - padding, point.y - popup.y - padding);
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    let (row, col) = ((y / cell) as usize, (x / cell) as usize);
+    (row < CALENDAR_ROWS && col < 7).then_some((row, col))
+}
+
+fn draw_outline(d: &mut RaylibDrawHandle, rect: Rectangle, outline_size: f32, color: Color) {
+    d.draw_line_ex(rvec2(rect.x, rect.y), rvec2(rect.x + rect.width, rect.y), outline_size, color);
+    d.draw_line_ex(rvec2(rect.x + rect.width, rect.y), rvec2(rect.x + rect.width, rect.y + rect.height), outline_size, color);
+    d.draw_line_ex(rvec2(rect.x + rect.width, rect.y + rect.height), rvec2(rect.x, rect.y + rect.height), outline_size, color);
+    d.draw_line_ex(rvec2(rect.x, rect.y + rect.height), rvec2(rect.x, rect.y), outline_size, color);
+}
+
+pub fn gui_date_input_update(rl: &mut RaylibHandle, idx: &mut i32, active_index: &mut i32, input: &mut DateInput, font: &UiFont, font_size: i32, rect: Rectangle) {
+    let (text_box, button) = date_input_rects(rect);
+    let my_idx = *idx;
+    gui_text_input_update(rl, idx, active_index, &mut input.input, font, font_size, text_box);
+
+    let mouse_on_button = unsafe { CheckCollisionPointRec(rl.get_mouse_position().into(), button.into()) };
+    let enter = *active_index == my_idx && rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+    if (rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && mouse_on_button) || enter {
+        *active_index = my_idx;
+        let shown = input.date().unwrap_or_else(|_| Local::now().date_naive());
+        input.calendar = Some(month_start(shown, 0));
+    }
+}
+
+/// Handles the open calendar of `input`: it covers the other widgets, so while it is open it gets
+/// all the mouse and keyboard input.
+pub fn gui_calendar_update(rl: &mut RaylibHandle, input: &mut DateInput, font_size: i32, rect: Rectangle) {
+    let month = match input.calendar {
+        Some(month) => month,
+        None => return,
+    };
+    let (popup, cell) = calendar_layout(rect, font_size, rl.get_screen_height());
+
+    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        input.calendar = None;
+        return;
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_LEFT) || is_key_typed(rl, KeyboardKey::KEY_PAGE_UP) {
+        input.calendar = Some(month_start(month, -1));
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_RIGHT) || is_key_typed(rl, KeyboardKey::KEY_PAGE_DOWN) {
+        input.calendar = Some(month_start(month, 1));
+    }
+
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        match calendar_cell(popup, cell, rl.get_mouse_position()) {
+            // a click outside closes the calendar, also the one on its button
+            None => input.calendar = None,
+            Some((0, 0)) => input.calendar = Some(month_start(month, -1)),
+            Some((0, 6)) => input.calendar = Some(month_start(month, 1)),
+            Some((row, col)) if (2..CALENDAR_ROWS - 1).contains(&row) => {
+                let day = calendar_day(month, row - 2, col);
+                if day.month() == month.month() {
+                    input.set_date(day);
+                    input.calendar = None;
+                }
+            },
+            Some((row, _)) if row == CALENDAR_ROWS - 1 => {
+                input.set_date(Local::now().date_naive());
+                input.calendar = None;
+            },
+            _ => {},
+        }
+    }
+}
+
+pub fn gui_date_input(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32, label: &str, input: &mut DateInput, field: Field) {
+    let (text_box, button) = date_input_rects(field.rect);
+    let is_active = *idx == active_idx;
+    gui_text_input(d, idx, active_idx, label, &mut input.input, Field { rect: text_box, ..field });
+
+    let fg = if is_active || input.is_calendar_open() {
+        Color::new(4, 146, 199, 255)
+    } else if unsafe { CheckCollisionPointRec(d.get_mouse_position().into(), button.into()) } {
+        Color::new(91, 178, 217, 255)
+    } else {
+        Color::new(104, 104, 104, 255)
+    };
+    draw_outline(d, button, 1.0, fg);
+
+    // a small page of a calendar, with the rings on top
+    let icon = rrect(button.x + button.width * 0.25, button.y + button.height * 0.3, button.width * 0.5, button.height * 0.45);
+    draw_outline(d, icon, 2.0, fg);
+    d.draw_rectangle_rec(rrect(icon.x, icon.y, icon.width, icon.height * 0.25), fg);
+    for ring_x in [icon.x + icon.width * 0.3, icon.x + icon.width * 0.7] {
+        d.draw_line_ex(rvec2(ring_x, icon.y - icon.height * 0.15), rvec2(ring_x, icon.y + icon.height * 0.1), 2.0, fg);
+    }
+}
+
+/// Draws the open calendar of `input`, after everything else so that it stays on top.
+pub fn gui_calendar(d: &mut RaylibDrawHandle, input: &DateInput, font: &UiFont, size: i32, rect: Rectangle) {
+    let month = match input.calendar {
+        Some(month) => month,
+        None => return,
+    };
+    let (popup, cell) = calendar_layout(rect, size, d.get_screen_height());
+    let padding = cell / 4.0;
+    let mouse = d.get_mouse_position();
+    let selected = input.date().ok();
+    let today = Local::now().date_naive();
+
+    let cell_rect = |row: usize, col: usize, cols: usize| rrect(popup.x + padding + col as f32 * cell, popup.y + padding + row as f32 * cell, cell * cols as f32, cell);
+    let centered = |d: &mut RaylibDrawHandle, text: &str, r: Rectangle, color: Color| {
+        let x = r.x + (r.width - font.measure(text, size) as f32) / 2.0;
+        let y = r.y + (r.height - size as f32) / 2.0;
+        font.draw(d, text, x as i32, y as i32, size, color);
+    };
+    let hovered = |r: Rectangle| unsafe { CheckCollisionPointRec(mouse.into(), r.into()) };
+
+    d.draw_rectangle_rec(popup, Color::new(36, 36, 36, 255));
+    draw_outline(d, popup, 1.0, Color::new(4, 146, 199, 255));
+
+    for (col, arrow) in [(0, "<"), (6, ">")] {
+        let r = cell_rect(0, col, 1);
+        if hovered(r) {
+            d.draw_rectangle_rec(r, Color::WHITE.alpha(0.15));
+        }
+        centered(d, arrow, r, Color::WHITE);
+    }
+    let title = format!("{} {}", MONTH_NAMES[month.month0() as usize], month.year());
+    centered(d, &title, cell_rect(0, 1, 5), Color::WHITE);
+
+    for (col, initial) in WEEKDAY_INITIALS.iter().enumerate() {
+        centered(d, initial, cell_rect(1, col, 1), Color::GRAY);
+    }
+
+    for week in 0..6 {
+        for col in 0..7 {
+            let day = calendar_day(month, week, col);
+            if day.month() != month.month() {
+                continue;
+            }
+            let r = cell_rect(week + 2, col, 1);
+            if selected == Some(day) {
+                d.draw_rectangle_rec(r, Color::new(4, 146, 199, 255));
+            } else if hovered(r) {
+                d.draw_rectangle_rec(r, Color::WHITE.alpha(0.15));
+            }
+            if day == today {
+                draw_outline(d, r, 1.0, Color::new(151, 232, 255, 255));
+            }
+            centered(d, &day.day().to_string(), r, Color::WHITE);
+        }
+    }
+
+    let r = cell_rect(CALENDAR_ROWS - 1, 0, 7);
+    if hovered(r) {
+        d.draw_rectangle_rec(r, Color::WHITE.alpha(0.15));
+    }
+    centered(d, "Oggi", r, Color::new(151, 232, 255, 255));
+}
+
+// choice of one item of a fixed list, which opens below the box
+#[derive(Debug, Clone, Default)]
+pub struct Dropdown {
+    selected: Option<usize>,
+    open: bool,
+}
+
+impl Dropdown {
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // moves the selection by `delta` items, without going past the ends of the list
+    fn move_selection(&mut self, delta: i32, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let current = self.selected.map(|i| i as i32).unwrap_or(if delta > 0 { -1 } else { count as i32 });
+        self.selected = Some((current + delta).clamp(0, count as i32 - 1) as usize);
+    }
+}
+
+// the open list goes below the box, or above it when there is no room, and the height of its items
+fn dropdown_list_layout(rect: Rectangle, count: usize, font_size: i32, screen_height: i32) -> (Rectangle, f32) {
+    let item_height = font_size as f32 * 1.8;
+    let height = item_height * count as f32;
+    let below = rect.y + rect.height;
+    let y = if below + height <= screen_height as f32 { below } else { (rect.y - height).max(0.0) };
+    (rrect(rect.x, y, rect.width, height), item_height)
+}
+
+pub fn gui_dropdown_update(rl: &mut RaylibHandle, idx: &mut i32, active_index: &mut i32, dropdown: &mut Dropdown, items: &[String], rect: Rectangle) {
+    let mouse_in_boundaries = unsafe { CheckCollisionPointRec(rl.get_mouse_position().into(), rect.into()) };
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        if mouse_in_boundaries {
+            *active_index = *idx;
+            dropdown.open = true;
+        } else if *active_index == *idx {
+            *active_index = -1;
+        }
+    }
+
+    if *idx == *active_index {
+        if is_key_typed(rl, KeyboardKey::KEY_UP) {
+            dropdown.move_selection(-1, items.len());
+        }
+        if is_key_typed(rl, KeyboardKey::KEY_DOWN) {
+            dropdown.move_selection(1, items.len());
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            dropdown.open = true;
+        }
+
+        // a letter selects the next item starting with it
+        while let Some(c) = rl.get_char_pressed() {
+            let starts_with = |i: usize| items[i].chars().next().is_some_and(|first| first.to_lowercase().eq(c.to_lowercase()));
+            let after = dropdown.selected.map_or(0, |i| i + 1);
+            if let Some(i) = (after..items.len()).chain(0..after).find(|i| starts_with(*i)) {
+                dropdown.selected = Some(i);
+            }
+        }
+    }
+
+    *idx += 1;
+}
+
+/// Handles the open list of `dropdown`: it covers the other widgets, so while it is open it gets
+/// all the mouse and keyboard input.
+pub fn gui_dropdown_list_update(rl: &mut RaylibHandle, dropdown: &mut Dropdown, items: &[String], font_size: i32, rect: Rectangle) {
+    if !dropdown.open {
+        return;
+    }
+    let (list, item_height) = dropdown_list_layout(rect, items.len(), font_size, rl.get_screen_height());
+
+    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+        dropdown.open = false;
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_UP) {
+        dropdown.move_selection(-1, items.len());
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_DOWN) {
+        dropdown.move_selection(1, items.len());
+    }
+
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        let mouse = rl.get_mouse_position();
+        // a click outside closes the list, also the one on the box
+        if unsafe { CheckCollisionPointRec(mouse.into(), list.into()) } {
+            dropdown.selected = Some((((mouse.y - list.y) / item_height) as usize).min(items.len() - 1));
+        }
+        dropdown.open = false;
+    }
+}
+
+pub fn gui_dropdown(d: &mut RaylibDrawHandle, idx: &mut i32, active_idx: i32, label: &str, dropdown: &Dropdown, items: &[String], field: Field) {
+    let Field { font, size, rect } = field;
+    let is_active = *idx == active_idx;
+
+    font.draw(d, label, rect.x as i32, rect.y as i32 - size, size, Color::WHITE);
+
+    let (fg, bg) = if is_active || dropdown.open {
+        (Color::new(4, 146, 199, 255), Color::new(151, 232, 255, 255))
+    } else if unsafe { CheckCollisionPointRec(d.get_mouse_position().into(), rect.into()) } {
+        (Color::new(91, 178, 217, 255), Color::WHITE.alpha(0.0))
+    } else {
+        (Color::new(104, 104, 104, 255), Color::WHITE.alpha(0.0))
+    };
+    d.draw_rectangle_rec(rect, bg);
+    draw_outline(d, rect, 1.0, fg);
+
+    let text_x = rect.x as i32 + text_input_padding(size);
+    let text_y = rect.y as i32 + (rect.height * 0.6) as i32 - size / 2;
+    match dropdown.selected.and_then(|i| items.get(i)) {
+        Some(item) => font.draw(d, item, text_x, text_y, size, fg),
+        None => font.draw(d, "Scegliere...", text_x, text_y, size, fg.alpha(0.5)),
+    }
+
+    // arrow pointing down
+    let arrow_x = rect.x + rect.width - rect.height / 2.0;
+    let arrow_y = rect.y + rect.height / 2.0;
+    let arrow_size = rect.height / 8.0;
+    d.draw_line_ex(rvec2(arrow_x - arrow_size, arrow_y - arrow_size / 2.0), rvec2(arrow_x, arrow_y + arrow_size / 2.0), 2.0, fg);
+    d.draw_line_ex(rvec2(arrow_x, arrow_y + arrow_size / 2.0), rvec2(arrow_x + arrow_size, arrow_y - arrow_size / 2.0), 2.0, fg);
+
+    *idx += 1;
+}
+
+/// Draws the open list of `dropdown`, after everything else so that it stays on top.
+pub fn gui_dropdown_list(d: &mut RaylibDrawHandle, dropdown: &Dropdown, items: &[String], font: &UiFont, size: i32, rect: Rectangle) {
+    if !dropdown.open {
+        return;
+    }
+    let (list, item_height) = dropdown_list_layout(rect, items.len(), size, d.get_screen_height());
+    let mouse = d.get_mouse_position();
+
+    d.draw_rectangle_rec(list, Color::new(36, 36, 36, 255));
+    for (i, item) in items.iter().enumerate() {
+        let r = rrect(list.x, list.y + item_height * i as f32, list.width, item_height);
+        if dropdown.selected == Some(i) {
+            d.draw_rectangle_rec(r, Color::new(4, 146, 199, 255));
+        } else if unsafe { CheckCollisionPointRec(mouse.into(), r.into()) } {
+            d.draw_rectangle_rec(r, Color::WHITE.alpha(0.15));
+        }
+        let text_y = r.y + (r.height - size as f32) / 2.0;
+        font.draw(d, item, r.x as i32 + text_input_padding(size), text_y as i32, size, Color::WHITE);
+    }
+    draw_outline(d, list, 1.0, Color::new(4, 146, 199, 255));
+}
+
+// modal list of lines to pick one from, like the events of an imported calendar
+#[derive(Debug, Clone)]
+pub struct Picker {
+    lines: Vec<String>,
+    selected: usize,
+    // first line shown
+    scroll: usize,
+    // whether the selected line has to be scrolled into view, when the picker opens or the keys
+    // move it; otherwise the scrolling of the mouse wheel is left alone
+    reveal_selected: bool,
+}
+
+pub enum PickerAction {
+    Pick(usize),
+    Close,
+}
 
-    gui_text_input(d, idx, active_idx, label, &mut seecret_data, size, text_box);
+impl Picker {
+    pub fn new(lines: Vec<String>, selected: usize) -> Picker {
+        Picker { selected: selected.min(lines.len().saturating_sub(1)), lines, scroll: 0, reveal_selected: true }
+    }
+
+    // scrolls just enough to show the selected line
+    fn show_selected(&mut self, visible: usize) {
+        self.scroll = self.scroll.min(self.selected).max((self.selected + 1).saturating_sub(visible));
+    }
+}
+
+// panel in the middle of the screen, height of the lines and how many of them fit below the title
+fn picker_layout(screen_width: i32, screen_height: i32, font_size: i32) -> (Rectangle, f32, usize) {
+    let line_height = font_size as f32 * 1.8;
+    let panel = rrect(screen_width as f32 * 0.2, screen_height as f32 * 0.2, screen_width as f32 * 0.6, screen_height as f32 * 0.6);
+    let visible = ((panel.height / line_height) as usize).saturating_sub(1).max(1);
+    (panel, line_height, visible)
+}
+
+// the line under the mouse, if any
+fn picker_line_at(picker: &Picker, panel: Rectangle, line_height: f32, visible: usize, mouse: Vector2) -> Option<usize> {
+    let inside = unsafe { CheckCollisionPointRec(mouse.into(), panel.into()) };
+    let row = ((mouse.y - panel.y) / line_height) as usize;
+    if !inside || row == 0 || row > visible {
+        return None;
+    }
+    Some(picker.scroll + row - 1).filter(|i| *i < picker.lines.len())
+}
+
+/// Handles the open picker, which covers everything else and so gets all the input.
+pub fn gui_picker_update(rl: &mut RaylibHandle, picker: &mut Picker, font_size: i32) -> Option<PickerAction> {
+    let (panel, line_height, visible) = picker_layout(rl.get_screen_width(), rl.get_screen_height(), font_size);
+    let last = picker.lines.len().saturating_sub(1);
+
+    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        return Some(PickerAction::Close);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_KP_ENTER) {
+        return Some(PickerAction::Pick(picker.selected));
+    }
+    let selected = picker.selected;
+    if is_key_typed(rl, KeyboardKey::KEY_UP) {
+        picker.selected = picker.selected.saturating_sub(1);
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_DOWN) {
+        picker.selected = (picker.selected + 1).min(last);
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_PAGE_UP) {
+        picker.selected = picker.selected.saturating_sub(visible);
+    }
+    if is_key_typed(rl, KeyboardKey::KEY_PAGE_DOWN) {
+        picker.selected = (picker.selected + visible).min(last);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_HOME) {
+        picker.selected = 0;
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_END) {
+        picker.selected = last;
+    }
+    if picker.selected != selected || picker.reveal_selected {
+        picker.show_selected(visible);
+        picker.reveal_selected = false;
+    }
+
+    let wheel = rl.get_mouse_wheel_move();
+    if wheel > 0.0 {
+        picker.scroll = picker.scroll.saturating_sub(1);
+    } else if wheel < 0.0 {
+        picker.scroll = (picker.scroll + 1).min(picker.lines.len().saturating_sub(visible));
+    }
+
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        let mouse = rl.get_mouse_position();
+        if !unsafe { CheckCollisionPointRec(mouse.into(), panel.into()) } {
+            return Some(PickerAction::Close);
+        }
+        if let Some(i) = picker_line_at(picker, panel, line_height, visible, mouse) {
+            return Some(PickerAction::Pick(i));
+        }
+    }
+
+    None
+}
+
+/// Draws the open picker, after everything else so that it stays on top.
+pub fn gui_picker(d: &mut RaylibDrawHandle, picker: &Picker, title: &str, font: &UiFont, size: i32) {
+    let (w, h) = (d.get_screen_width(), d.get_screen_height());
+    let (panel, line_height, visible) = picker_layout(w, h, size);
+    let padding = text_input_padding(size);
+    let hovered = picker_line_at(picker, panel, line_height, visible, d.get_mouse_position());
+
+    d.draw_rectangle(0, 0, w, h, Color::BLACK.alpha(0.6));
+    d.draw_rectangle_rec(panel, Color::new(36, 36, 36, 255));
+    font.draw(d, title, panel.x as i32 + padding, (panel.y + (line_height - size as f32) / 2.0) as i32, size, Color::GRAY);
+
+    for (row, i) in (picker.scroll..picker.lines.len()).take(visible).enumerate() {
+        let r = rrect(panel.x, panel.y + line_height * (row + 1) as f32, panel.width, line_height);
+        if i == picker.selected {
+            d.draw_rectangle_rec(r, Color::new(4, 146, 199, 255));
+        } else if hovered == Some(i) {
+            d.draw_rectangle_rec(r, Color::WHITE.alpha(0.15));
+        }
+
+        // lines too long are cut, like the text fields do
+        let mut line = picker.lines[i].clone();
+        let max_width = r.width as i32 - padding * 2;
+        if font.measure(&line, size) > max_width {
+            while !line.is_empty() && font.measure(&line, size) + font.measure("...", size) > max_width {
+                line.pop();
+            }
+            line.push_str("...");
+        }
+        font.draw(d, &line, r.x as i32 + padding, (r.y + (line_height - size as f32) / 2.0) as i32, size, Color::WHITE);
+    }
+    draw_outline(d, panel, 1.0, Color::new(4, 146, 199, 255));
 }
 
 pub fn gui_check_box_update(rl: &mut RaylibHandle, idx: &mut i32, active_idx: &mut i32, check_box: Rectangle, checked: &mut bool) {
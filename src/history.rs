@@ -0,0 +1,115 @@
+use crate::{rotate_image, session::ImgMeta, ImgData};
+
+// oldest edits are forgotten past this point
+const MAX_HISTORY: usize = 256;
+
+pub enum Edit {
+    Remove(usize),
+    // boxed, as the images are much larger than the other edits
+    Insert(usize, Box<ImgData>),
+    Swap(usize, usize),
+    Move(usize, usize),
+    Rotate(usize, bool),
+    SetMeta(usize, ImgMeta),
+    // several edits undone and redone together, applied in order
+    Batch(Vec<Edit>),
+}
+
+impl Edit {
+    /// Applies the edit to `images` and returns the edit that reverts it.
+    fn apply(self, images: &mut Vec<ImgData>) -> Edit {
+        use Edit::*;
+        match self {
+            Remove(i) => {
+                let mut img = images.remove(i);
+                // `unload_previews` only counts the shown images, the preview is loaded again
+                // from the cache if the photo is put back
+                img.unload();
+                Insert(i, Box::new(img))
+            },
+            Insert(i, img) => {
+                images.insert(i, *img);
+                Remove(i)
+            },
+            Swap(a, b) => {
+                images.swap(a, b);
+                Swap(a, b)
+            },
+            Move(from, to) => {
+                let img = images.remove(from);
+                images.insert(to, img);
+                Move(to, from)
+            },
+            Rotate(i, clockwise) => {
+                rotate_image(&mut images[i], clockwise);
+                Rotate(i, !clockwise)
+            },
+            SetMeta(i, meta) => {
+                let old_meta = std::mem::replace(&mut images[i].meta, meta);
+                // the texture shows the redacted areas
+                if images[i].meta.redactions != old_meta.redactions {
+                    images[i].texture = None;
+                }
+                SetMeta(i, old_meta)
+            },
+            Batch(edits) => {
+                let mut inverses = edits.into_iter()
+                    .map(|e| e.apply(images))
+                    .collect::<Vec<_>>();
+                inverses.reverse();
+                Batch(inverses)
+            },
+        }
+    }
+
+    /// Index of the image that should become active after the edit is applied.
+    fn target(&self) -> Option<usize> {
+        use Edit::*;
+        match self {
+            Remove(i) | Insert(i, _) | Rotate(i, _) | SetMeta(i, _) => Some(*i),
+            Swap(_, b) => Some(*b),
+            Move(_, to) => Some(*to),
+            Batch(edits) => edits.first().and_then(|e| e.target()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl History {
+    pub fn apply(&mut self, edit: Edit, images: &mut Vec<ImgData>) {
+        if let Edit::Batch(edits) = &edit {
+            if edits.is_empty() {
+                return;
+            }
+        }
+
+        let inverse = edit.apply(images);
+        self.undo_stack.push(inverse);
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Returns the index of the image touched by the reverted edit, if there was anything to undo.
+    pub fn undo(&mut self, images: &mut Vec<ImgData>) -> Option<usize> {
+        let edit = self.undo_stack.pop()?;
+        let target = edit.target();
+        let inverse = edit.apply(images);
+        self.redo_stack.push(inverse);
+        target
+    }
+
+    pub fn redo(&mut self, images: &mut Vec<ImgData>) -> Option<usize> {
+        let edit = self.redo_stack.pop()?;
+        let target = edit.target();
+        let inverse = edit.apply(images);
+        self.undo_stack.push(inverse);
+        target
+    }
+}
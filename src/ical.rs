@@ -0,0 +1,220 @@
+// Minimal reader of iCalendar (`.ics`) files, enough to prefill the `Dati` tab from the events of
+// the yearly programme: only title, days and categories of each event are read. Repeated events
+// give only their first occurrence.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub summary: String,
+    pub start: NaiveDate,
+    // last day of events of more days
+    pub end: Option<NaiveDate>,
+    pub categories: Vec<String>,
+}
+
+// the event being read, until its `END:VEVENT`
+#[derive(Default)]
+struct PartialEvent {
+    summary: Option<String>,
+    start: Option<(NaiveDate, bool)>,
+    end: Option<(NaiveDate, bool)>,
+    duration_days: Option<i64>,
+    categories: Vec<String>,
+}
+
+impl PartialEvent {
+    fn finish(self) -> Option<Event> {
+        let (start, all_day) = self.start?;
+        let end = match (self.end, self.duration_days) {
+            // the end of all day events is the day after the last one
+            (Some((end, true)), _) => end - Duration::days(1),
+            (Some((end, false)), _) => end,
+            (None, Some(days)) if all_day => start + Duration::days(days - 1),
+            (None, _) => start,
+        };
+        Some(Event {
+            summary: self.summary.unwrap_or_default(),
+            start,
+            end: (end > start).then_some(end),
+            categories: self.categories,
+        })
+    }
+}
+
+// lines of the file, with the folded ones (continuing with a space or a tab) joined back
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+// name (in capital letters) and value of a content line, the parameters are not needed
+fn split_line(line: &str) -> Option<(String, &str)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    })?.0;
+
+    let name = line[..colon].split(';').next()?.to_uppercase();
+    Some((name, &line[colon + 1..]))
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => text.push(' '),
+            Some(c) => text.push(c),
+            None => {},
+        }
+    }
+    text.trim().to_owned()
+}
+
+// values separated by commas that are not escaped
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    items.iter().map(|item| unescape(item)).filter(|item| !item.is_empty()).collect()
+}
+
+// the day of a `DATE` or `DATE-TIME` value, and whether it is only a day
+fn parse_date(value: &str) -> Option<(NaiveDate, bool)> {
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    Some((date, value.len() == 8))
+}
+
+// days of a `DURATION` made only of weeks and days, like `P3D`
+fn parse_duration_days(value: &str) -> Option<i64> {
+    let value = value.strip_prefix('P')?;
+    if let Some(weeks) = value.strip_suffix('W') {
+        return Some(weeks.parse::<i64>().ok()? * 7);
+    }
+    value.strip_suffix('D')?.parse().ok()
+}
+
+/// Events of the calendar in `text`, sorted by their first day.
+pub fn parse(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut event: Option<PartialEvent> = None;
+    // depth of the components inside the event (like alarms), whose properties are skipped
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let (name, value) = match split_line(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        let current = match (&mut event, name.as_str()) {
+            (None, "BEGIN") if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(PartialEvent::default());
+                nested = 0;
+                continue;
+            },
+            (None, _) => continue,
+            (Some(_), "BEGIN") => {
+                nested += 1;
+                continue;
+            },
+            (Some(_), "END") if nested > 0 => {
+                nested -= 1;
+                continue;
+            },
+            (Some(_), "END") => {
+                if let Some(event) = event.take().and_then(PartialEvent::finish) {
+                    events.push(event);
+                }
+                continue;
+            },
+            (Some(_), _) if nested > 0 => continue,
+            (Some(current), _) => current,
+        };
+
+        match name.as_str() {
+            "SUMMARY" => current.summary = Some(unescape(value)),
+            "DTSTART" => current.start = parse_date(value),
+            "DTEND" => current.end = parse_date(value),
+            "DURATION" => current.duration_days = parse_duration_days(value),
+            "CATEGORIES" => current.categories.extend(split_list(value)),
+            _ => {},
+        }
+    }
+
+    events.sort_by_key(|event| event.start);
+    events
+}
+
+/// Reads the events of the calendar file at `path`, the error is meant to be shown to the user.
+pub fn load(path: &Path) -> Result<Vec<Event>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Impossibile leggere il calendario `{}`: {}", path.display(), e))?;
+    let events = parse(&String::from_utf8_lossy(&bytes));
+    if events.is_empty() {
+        return Err(format!("Il calendario `{}` non contiene eventi.", path.display()));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn all_day_events_end_the_day_before_dtend() {
+        let events = parse("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Campo estivo\r\nDTSTART;VALUE=DATE:20240801\r\nDTEND;VALUE=DATE:20240811\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nSUMMARY:Uscita\r\nDTSTART;VALUE=DATE:20240914\r\nDTEND;VALUE=DATE:20240915\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n");
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].start, events[0].end), (day(2024, 8, 1), Some(day(2024, 8, 10))));
+        assert_eq!((events[1].start, events[1].end), (day(2024, 9, 14), None));
+    }
+
+    #[test]
+    fn timed_events_and_durations() {
+        let events = parse("BEGIN:VEVENT\nDTSTART:20241005T150000Z\nDTEND:20241006T120000Z\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20241102\nDURATION:P3D\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20241201\nDURATION:P1W\nEND:VEVENT\n");
+        assert_eq!((events[0].start, events[0].end), (day(2024, 10, 5), Some(day(2024, 10, 6))));
+        assert_eq!(events[0].summary, "");
+        assert_eq!((events[1].start, events[1].end), (day(2024, 11, 2), Some(day(2024, 11, 4))));
+        assert_eq!((events[2].start, events[2].end), (day(2024, 12, 1), Some(day(2024, 12, 7))));
+    }
+
+    #[test]
+    fn folded_and_escaped_text() {
+        let events = parse("BEGIN:VEVENT\r\nSUMMARY:Uscita al lago\\, con\r\n  pernotto\\nin tenda\r\nCATEGORIES:ESPLO,Reparto\\, Alta Squadriglia\r\nCATEGORIES:PIO\r\nDTSTART;TZID=\"Europe/Rome: ora\":20240301T090000\r\nEND:VEVENT\r\n");
+        assert_eq!(events[0].summary, "Uscita al lago, con pernotto in tenda");
+        assert_eq!(events[0].categories, ["ESPLO", "Reparto, Alta Squadriglia", "PIO"]);
+        assert_eq!(events[0].start, day(2024, 3, 1));
+    }
+
+    #[test]
+    fn nested_components_and_sorting() {
+        let events = parse("BEGIN:VEVENT\nSUMMARY:Dopo\nDTSTART;VALUE=DATE:20240510\nBEGIN:VALARM\nSUMMARY:Promemoria\nDTSTART:20240101T000000\nEND:VALARM\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:Prima\nDTSTART;VALUE=DATE:20240420\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:Senza data\nEND:VEVENT\n");
+        assert_eq!(events.iter().map(|event| event.summary.as_str()).collect::<Vec<_>>(), ["Prima", "Dopo"]);
+        assert_eq!(events[1].start, day(2024, 5, 10));
+    }
+}
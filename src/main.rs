@@ -6,28 +6,56 @@ extern crate strum;
 extern crate path_slash;
 extern crate walkdir;
 
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 use ffi::{GetCurrentMonitor, GetMonitorHeight, GetMonitorWidth};
 use ftp::FtpStream;
-use gui::{check_ctrl_shortcut, draw_outlined_text, gui_check_box, gui_check_box_update, gui_number_input_update, gui_seecret_text_input, gui_text_input, gui_text_input_update, is_key_pressed_repeat};
-use image::ImageReader;
-use image::{GenericImageView, DynamicImage};
+use gui::{check_ctrl_shortcut, draw_outlined_text, format_date, gui_calendar, gui_calendar_update, gui_check_box, gui_check_box_update, gui_date_input, gui_date_input_update, gui_dropdown, gui_dropdown_list, gui_dropdown_list_update, gui_dropdown_update, gui_picker, gui_picker_update, gui_text_input, gui_text_input_update, is_key_pressed_repeat, DateInput, Dropdown, Field, Picker, PickerAction, TextInput, UiFont};
+use image::DynamicImage;
 use path_slash::PathBufExt as _;
-use image::imageops::FilterType::Lanczos3;
+use config::Config;
+use exif::Exif;
+use history::{Edit, History};
+use ical::Event;
+use metadata::Metadata;
+use naming::{Activity, Naming, Photo};
+use quality::Quality;
+use redact::{Effect, Redaction, Shape};
+use viewer::{Compare, Viewer, ZoomPan};
+use session::{ImgMeta, PickFlag, MAX_RATING};
+use slug::slug;
+use texture::texture_from_image;
 use raylib::ffi::CheckCollisionPointRec;
 use raylib::prelude::*;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use walkdir::WalkDir;
 
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::ffi::{CString, c_void};
-use std::path::PathBuf;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{fmt, fs, io, thread, vec};
 use std::io::{Cursor, Write};
 
+mod branca;
+mod cache;
+mod config;
+mod console;
+mod exif;
 mod gui;
+mod history;
+mod ical;
+mod metadata;
+mod naming;
+mod quality;
+mod redact;
+mod season;
+mod session;
+mod slug;
+mod texture;
+mod viewer;
+mod watermark;
 
 const SMALLER_DIMENSION: u32 = 600;
 const BIGGER_DIMENSION: u32 = 800;
@@ -35,22 +63,134 @@ const BIGGER_DIMENSION: u32 = 800;
 const HD_SMALLER_DIMENSION: u32 = 1200;
 const HD_BIGGER_DIMENSION: u32 = 1600;
 
+const RATING_KEYS: [KeyboardKey; MAX_RATING as usize + 1] = [
+    KeyboardKey::KEY_ZERO,
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+];
+
+// seconds between two steps of the list scrolling while dragging near its edges
+const AUTOSCROLL_INTERVAL: f64 = 0.1;
+
+const DEFAULT_GRID_THUMB_SIZE: f32 = 160.0;
+const MIN_GRID_THUMB_SIZE: f32 = 64.0;
+const MAX_GRID_THUMB_SIZE: f32 = 512.0;
+const GRID_THUMB_SIZE_STEP: f32 = 16.0;
+
 const THEME_COLOR: Color = Color::new(85, 138, 255, 255);
 const BACKGROUND_COLOR: Color = Color::new(0x18, 0x18, 0x18, 0xff);
 
-fn clean_string(s: String) -> String {
-    s.replace(" ", "")
-    .replace("\t", "")
-    .replace("\n", "")
-    .replace("\r", "")
+// first and, for activities of more days, last day of the activity
+fn date_range(date: &DateInput, end_date: &DateInput) -> Result<(NaiveDate, Option<NaiveDate>), String> {
+    let date = date.date()?;
+    if end_date.is_empty() {
+        return Ok((date, None));
+    }
+    let end_date = end_date.date()?;
+    if end_date < date {
+        return Err(format!("La data di fine ({}) è precedente a quella di inizio ({}).", format_date(end_date), format_date(date)));
+    }
+    Ok((date, (end_date > date).then_some(end_date)))
+}
+
+// what is typed in the `Dati` tab, as it appears in the names of the saved folder and files
+fn activity_from_inputs(titolo: &TextInput, branca: &Dropdown, date: &DateInput, end_date: &DateInput, config: &Config) -> Result<Activity, String> {
+    let (date, end_date) = date_range(date, end_date)?;
+    let branca = branca.selected()
+        .and_then(|i| config.branche.get(i))
+        .ok_or_else(|| String::from("Manca la branca dell'attività."))?;
+    Ok(Activity {
+        date,
+        end_date,
+        // only the title is shortened, the branca is one of the names in the settings
+        branca: slug(&branca.name, config.slug_style, usize::MAX),
+        title: slug(titolo.as_str(), config.slug_style, config.title_max_len),
+    })
+}
+
+fn is_calendar_path(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ics"))
+}
+
+// how an event of an imported calendar is listed
+fn event_line(event: &Event) -> String {
+    let days = match event.end {
+        Some(end) => format!("{} - {}", format_date(event.start), format_date(end)),
+        None => format_date(event.start),
+    };
+    if event.categories.is_empty() {
+        format!("{}   {}", days, event.summary)
+    } else {
+        format!("{}   {}   [{}]", days, event.summary, event.categories.join(", "))
+    }
+}
+
+// metadata of the saved photos, with the title and the branca as they are written and not as in
+// the file names; the photographer is added for each photo
+fn activity_metadata(titolo: &TextInput, branca: &Dropdown, activity: &Activity, config: &Config) -> Metadata {
+    Metadata {
+        title: titolo.as_str().trim().to_owned(),
+        branca: branca.selected().and_then(|i| config.branche.get(i)).map(|b| b.name.clone()).unwrap_or_default(),
+        date: activity.date,
+        end_date: activity.end_date,
+        copyright: config.copyright.clone(),
+        licence: config.licence.clone(),
+        ..Metadata::default()
+    }
+}
+
+// fills the `Dati` tab with an event of an imported calendar, the branca is the first one among
+// its categories
+fn apply_event(event: &Event, titolo: &mut TextInput, branca: &mut Dropdown, date: &mut DateInput, end_date: &mut DateInput, config: &Config) {
+    titolo.set_text(&event.summary);
+    date.set_date(event.start);
+    match event.end {
+        Some(end) => end_date.set_date(end),
+        None => end_date.clear(),
+    }
+    let index = event.categories.iter().find_map(|category| config.branche.iter().position(|b| b.matches(category)));
+    if index.is_some() {
+        branca.select(index);
+    }
+}
+
+// indices of the photos that are going to be saved, rejected photos are never saved
+fn photos_to_save(images: &[ImgData], picks_only: bool) -> Vec<usize> {
+    images.iter()
+        .enumerate()
+        .filter(|(_, img)| if picks_only { img.meta.flag == PickFlag::Pick } else { img.meta.flag != PickFlag::Reject })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// photos whose EXIF date is not a day of the activity, with that date; photos without a date are
+// not checked
+fn photos_outside_dates(images: &[ImgData], indices: &[usize], activity: &Activity) -> Vec<(usize, NaiveDate)> {
+    indices.iter()
+        .filter_map(|i| Some((*i, images[*i].exif.taken?.date())))
+        .filter(|(_, taken)| !activity.contains(*taken))
+        .collect()
+}
+
+// `number` counts from 1 in the saved photos; only the title is shortened, these are kept whole
+fn photo_info(img: &ImgData, number: usize, config: &Config) -> Photo {
+    let original = img.path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    Photo {
+        number,
+        original: slug(&original, config.slug_style, usize::MAX),
+        photographer: slug(img.exif.artist.as_deref().unwrap_or_default(), config.slug_style, usize::MAX),
+    }
 }
 
-fn check_single_image_path(p: PathBuf, images: &mut Vec<PathBuf>){
+fn check_single_image_path(p: PathBuf, meta: &ImgMeta, images: &mut Vec<(PathBuf, ImgMeta)>){
     match p.try_exists() {
         Ok(true) => {
             if let Some(extension) = p.clone().extension() {
                 if extension == "jpeg" || extension == "jpg" || extension == "JPG" || extension == "png" || extension == "PNG" {
-                    images.push(p);
+                    images.push((p, meta.clone()));
                 } else if extension == "txt" {
                     let content = fs::read_to_string(p).unwrap_or_default();
                     let nps = content.lines()
@@ -66,9 +206,10 @@ fn check_single_image_path(p: PathBuf, images: &mut Vec<PathBuf>){
     }
 }
 
-fn check_images_paths(files: &Vec<&str>) -> Vec<PathBuf> {
+fn check_images_paths(files: &Vec<&str>) -> Vec<(PathBuf, ImgMeta)> {
     let mut images = vec![];
     for f in files {
+        let (f, meta) = session::parse_line(f);
         let p = PathBuf::from(f);
         if p.is_dir() {
             let mut entries = WalkDir::new(p)
@@ -79,10 +220,10 @@ fn check_images_paths(files: &Vec<&str>) -> Vec<PathBuf> {
             entries.sort();
             for entry in entries {
                 let np = PathBuf::from(entry);
-                check_single_image_path(np, &mut images);
+                check_single_image_path(np, &meta, &mut images);
             }
         } else {
-            check_single_image_path(p, &mut images);
+            check_single_image_path(p, &meta, &mut images);
         }
         
     }
@@ -94,17 +235,20 @@ fn check_images_paths(files: &Vec<&str>) -> Vec<PathBuf> {
 fn save_used_files(path: &str,  images: &Vec<ImgData>) -> io::Result<()> {
     let mut f = fs::File::create(path)?;
     for img in images.iter() {
-        writeln!(&mut f, "{}", img.path.display())?;
+        writeln!(&mut f, "{}", session::format_line(&img.path.display().to_string(), &img.meta))?;
     }
 
     Ok(())
 }
 
+// files directly in `dir`, the subfolders (like the one of the private photos) are skipped
 fn find_files(dir: &str) -> Vec<PathBuf> {
     let mut paths: Vec<PathBuf> = Vec::new();
     for element in std::path::Path::new(dir).read_dir().unwrap() {
         let path = element.unwrap().path();
-        paths.push(path);
+        if path.is_file() {
+            paths.push(path);
+        }
     }
 
     paths.sort();
@@ -112,24 +256,401 @@ fn find_files(dir: &str) -> Vec<PathBuf> {
     return paths;
 }
 
+// the files among `paths` that contain a GPS position, or that cannot be read to check it
+fn files_with_gps(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths.iter()
+        .filter(|path| fs::read(path).map_or(true, |bytes| exif::has_gps(&bytes)))
+        .cloned()
+        .collect()
+}
+
 struct ImgData {
     path: PathBuf,
     filename: String,
-    image: DynamicImage,
-    texture: Texture2D,
+    // unrotated preview on disk, without it the preview can never be dropped from memory
+    cached: Option<PathBuf>,
+    // size of the unrotated preview
+    size: (u32, u32),
+    exif: Exif,
+    // preview, rotated following `meta`, loaded and dropped on demand
+    image: Option<DynamicImage>,
+    texture: Option<Texture2D>,
+    // frame in which the preview was last needed
+    last_used: u64,
+    quality: Quality,
+    meta: ImgMeta,
+    selected: bool,
 }
 
 impl ImgData {
-    pub fn new(path: PathBuf, filename: String, image: DynamicImage, texture: Texture2D) -> ImgData {
+    pub fn new(path: PathBuf, filename: String, preview: cache::Preview, quality: Quality, meta: ImgMeta, frame: u64) -> ImgData {
+        let size = (preview.image.width(), preview.image.height());
+        let image = rotate_quarter_turns(preview.image, meta.rotation);
         ImgData {
             path,
             filename,
-            image,
-            texture
+            cached: preview.cached,
+            size,
+            exif: preview.exif,
+            image: Some(image),
+            texture: None,
+            last_used: frame,
+            quality,
+            meta,
+            selected: false,
+        }
+    }
+
+    // size of the preview as shown, after rotation
+    fn dimensions(&self) -> (u32, u32) {
+        if self.meta.rotation % 2 == 1 {
+            (self.size.1, self.size.0)
+        } else {
+            self.size
+        }
+    }
+
+    fn load_image(&mut self) -> Option<&DynamicImage> {
+        if self.image.is_none() {
+            let cached = self.cached.as_ref()?;
+            match image::open(cached) {
+                Ok(image) => self.image = Some(rotate_quarter_turns(image, self.meta.rotation)),
+                Err(e) => eprintln!("[ERROR]: Impossibile leggere l'anteprima di `{}`: {}", self.path.display(), e),
+            }
+        }
+        self.image.as_ref()
+    }
+
+    // the preview with the redacted areas hidden, as it is shown and saved
+    fn redacted_image(&mut self) -> Option<Cow<'_, DynamicImage>> {
+        self.load_image()?;
+        let image = self.image.as_ref()?;
+        if self.meta.redactions.is_empty() {
+            Some(Cow::Borrowed(image))
+        } else {
+            Some(Cow::Owned(redact::apply(image, &self.meta.redactions, self.meta.rotation)))
+        }
+    }
+
+    // makes sure that the texture exists, since it is about to be drawn in `frame`
+    fn load_texture(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, frame: u64) {
+        self.last_used = frame;
+        if self.texture.is_none() {
+            if let Some(image) = self.redacted_image() {
+                self.texture = texture_from_image(rl, thread, &image);
+            }
+        }
+    }
+
+    // bytes of preview pixels currently held, in memory and on the GPU
+    fn resident_bytes(&self) -> usize {
+        let bytes = self.size.0 as usize * self.size.1 as usize * 4;
+        bytes * (self.image.is_some() as usize + self.texture.is_some() as usize)
+    }
+
+    fn unload(&mut self) {
+        self.texture = None;
+        if self.cached.is_some() {
+            self.image = None;
+        }
+    }
+}
+
+// size of the full resolution texture, or of the preview if the original could not be loaded
+fn shown_size(texture: &Option<Texture2D>, img: &ImgData) -> Vector2 {
+    match texture {
+        Some(texture) => rvec2(texture.width(), texture.height()),
+        None => rvec2(img.dimensions().0, img.dimensions().1),
+    }
+}
+
+fn rotate_quarter_turns(image: DynamicImage, rotation: u8) -> DynamicImage {
+    match rotation % 4 {
+        1 => image.rotate90(),
+        2 => image.rotate180(),
+        3 => image.rotate270(),
+        _ => image,
+    }
+}
+
+// drops the least recently used previews not needed in `frame` until they fit in `budget` bytes
+fn unload_previews(images: &mut [ImgData], budget: usize, frame: u64) {
+    let mut resident = images.iter().map(ImgData::resident_bytes).sum::<usize>();
+    if resident <= budget {
+        return;
+    }
+
+    let mut candidates = (0..images.len())
+        .filter(|i| images[*i].last_used < frame && images[*i].resident_bytes() > 0)
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|i| images[*i].last_used);
+
+    for i in candidates {
+        if resident <= budget {
+            break;
+        }
+        resident -= images[i].resident_bytes();
+        images[i].unload();
+        resident += images[i].resident_bytes();
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum ListFilter {
+    All,
+    Flagged,
+    Picked,
+    NotRejected,
+    Rejected,
+}
+
+impl ListFilter {
+    fn next(self) -> ListFilter {
+        use ListFilter::*;
+        match self {
+            All => Flagged,
+            Flagged => Picked,
+            Picked => NotRejected,
+            NotRejected => Rejected,
+            Rejected => All,
+        }
+    }
+
+    fn accepts(self, img: &ImgData, min_rating: u8) -> bool {
+        use ListFilter::*;
+        img.meta.rating >= min_rating && match self {
+            All => true,
+            Flagged => img.quality.is_flagged(),
+            Picked => img.meta.flag == PickFlag::Pick,
+            NotRejected => img.meta.flag != PickFlag::Reject,
+            Rejected => img.meta.flag == PickFlag::Reject,
+        }
+    }
+}
+
+impl fmt::Display for ListFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ListFilter::*;
+        write!(f, "{}", match self {
+            All => "tutte",
+            Flagged => "segnalate",
+            Picked => "scelte",
+            NotRejected => "non scartate",
+            Rejected => "scartate",
+        })
+    }
+}
+
+fn rotate_image(img: &mut ImgData, clockwise: bool) {
+    let quarter_turns = if clockwise { 1 } else { 3 };
+    img.image = img.image.take().map(|image| rotate_quarter_turns(image, quarter_turns));
+    // recreated from the rotated image the next time it is drawn
+    img.texture = None;
+    img.meta.rotation = (img.meta.rotation + quarter_turns) % 4;
+}
+
+// smallest side of a redacted area, in fractions of the photo, so that a click does not add one
+const MIN_REDACTION_SIZE: f32 = 0.01;
+
+// redaction mode of the `Foto` tab, where the areas to hide are drawn on the active photo
+#[derive(Debug, Copy, Clone, Default)]
+struct RedactTool {
+    // of the next areas
+    shape: Shape,
+    effect: Effect,
+    // corner where the area being drawn was started, in fractions of the photo as shown
+    drag_start: Option<Vector2>,
+}
+
+impl RedactTool {
+    // the area from `start` to `end`, in fractions of the photo as shown
+    fn area(&self, start: Vector2, end: Vector2) -> Redaction {
+        let (x0, x1) = (start.x.min(end.x).clamp(0.0, 1.0), start.x.max(end.x).clamp(0.0, 1.0));
+        let (y0, y1) = (start.y.min(end.y).clamp(0.0, 1.0), start.y.max(end.y).clamp(0.0, 1.0));
+        Redaction { shape: self.shape, effect: self.effect, x: x0, y: y0, width: x1 - x0, height: y1 - y0 }
+    }
+}
+
+// outline of an area (in fractions of the photo as shown) over the photo drawn in `photo_rect`
+fn draw_redaction_outline(d: &mut RaylibDrawHandle, area: &Redaction, photo_rect: Rectangle, color: Color) {
+    let rect = rrect(
+        photo_rect.x + area.x * photo_rect.width,
+        photo_rect.y + area.y * photo_rect.height,
+        area.width * photo_rect.width,
+        area.height * photo_rect.height,
+    );
+    match area.shape {
+        Shape::Rectangle => d.draw_rectangle_lines_ex(rect, 2.0, color),
+        Shape::Ellipse => d.draw_ellipse_lines((rect.x + rect.width / 2.0) as i32, (rect.y + rect.height / 2.0) as i32, rect.width / 2.0, rect.height / 2.0, color),
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ListDrag {
+    // position in the view of the photo that was pressed
+    from: i32,
+    start: Vector2,
+    dragging: bool,
+}
+
+// images affected by bulk operations: the selected ones or, if nothing is selected, the active one
+fn operation_targets(images: &[ImgData], view: &[usize], active: i32) -> Vec<usize> {
+    let selected = view.iter()
+        .copied()
+        .filter(|i| images[*i].selected)
+        .collect::<Vec<_>>();
+
+    if selected.is_empty() {
+        view.get(active as usize).copied().into_iter().collect()
+    } else {
+        selected
+    }
+}
+
+// selection changes for a click on the photo at position `clicked` of the view
+fn click_image(images: &mut [ImgData], view: &[usize], clicked: i32, active: i32, anchor: &mut i32, ctrl: bool, shift: bool) {
+    if ctrl {
+        if !images.iter().any(|img| img.selected) {
+            // starting a selection from the active photo
+            if let Some(active_image) = view.get(active as usize) {
+                images[*active_image].selected = true;
+            }
+        }
+        images[view[clicked as usize]].selected ^= true;
+        *anchor = clicked;
+    } else if shift {
+        select_range(images, view, *anchor, clicked);
+    } else {
+        // a selected photo may be the start of a drag of the whole selection
+        if !images[view[clicked as usize]].selected {
+            clear_selection(images);
         }
+        *anchor = clicked;
     }
 }
 
+// moves the dragged photo (or the whole selection, if the photo is part of it) before the photo at
+// `drop_position` in the view, returns the index of the first moved photo
+fn drop_images(history: &mut History, images: &mut Vec<ImgData>, view: &[usize], dragged: usize, drop_position: i32, active: i32) -> usize {
+    let targets = if images[dragged].selected {
+        operation_targets(images, view, active)
+    } else {
+        vec![dragged]
+    };
+    let before_image = view.get(drop_position as usize).copied().unwrap_or_else(|| view[view.len() - 1] + 1);
+    let position = position_before(&targets, before_image);
+    history.apply(Edit::Batch(move_to_position(&targets, position, images.len())), images);
+    position
+}
+
+// where the first of the (sorted) `targets` ends up when they are moved before the photo that is
+// now at `before_image`, once they are no longer in front of it
+fn position_before(targets: &[usize], before_image: usize) -> usize {
+    before_image - targets.iter().filter(|t| **t < before_image).count()
+}
+
+fn draw_thumbnail(d: &mut RaylibDrawHandle, img: &ImgData, number: usize, bounds: Rectangle, highlighted: bool, font_size: i32) {
+    let img_w = img.dimensions().0 as f32;
+    let img_h = img.dimensions().1 as f32;
+    let scale_x = bounds.width / img_w;
+    let scale_y = bounds.height / img_h;
+    let mut scale = scale_x.min(scale_y);
+
+    let mut color_fade = 1.0;
+    
+    if !highlighted {
+        scale *= 0.85;
+        color_fade *= 0.9;
+    }
+
+    let y = bounds.y + (bounds.height - img_h * scale) / 2.0;
+    let x = bounds.x + (bounds.width - img_w * scale) / 2.0;
+    
+    let tint = if img.meta.flag == PickFlag::Reject { Color::WHITE.alpha(0.35) } else { Color::WHITE };
+    match &img.texture {
+        Some(texture) => d.draw_texture_ex(texture, rvec2(x, y), 0.0, scale, tint),
+        None => d.draw_rectangle_rec(rrect(x, y, img_w * scale, img_h * scale), Color::DARKGRAY),
+    }
+    match img.meta.flag {
+        PickFlag::Unflagged => {},
+        PickFlag::Pick => d.draw_rectangle_lines_ex(rrect(x, y, img_w * scale, img_h * scale), 3.0, Color::LIME),
+        PickFlag::Reject => d.draw_rectangle_lines_ex(rrect(x, y, img_w * scale, img_h * scale), 3.0, Color::RED),
+    }
+    
+    let num_text = format!("{}", number);
+    let outline_size = 2;
+    draw_outlined_text(d, &num_text, x as i32 + outline_size * 2, y as i32 + outline_size + 1, font_size, outline_size, Color::WHITE.alpha(color_fade), Color::BLACK.alpha(color_fade/2.0));
+
+    if img.meta.rating > 0 {
+        let stars_text = "*".repeat(img.meta.rating as usize);
+        let stars_width = d.measure_text(&stars_text, font_size);
+        draw_outlined_text(d, &stars_text, (x + img_w * scale) as i32 - stars_width - outline_size * 2, y as i32 + outline_size + 1, font_size, outline_size, Color::GOLD.alpha(color_fade), Color::BLACK.alpha(color_fade/2.0));
+    }
+
+    // bottom left corner of the thumbnail
+    if img.meta.private {
+        let badge = "PRIVATA";
+        let badge_width = d.measure_text(badge, font_size) + outline_size * 2;
+        let badge_x = x as i32 + outline_size * 2;
+        let badge_y = (y + img_h * scale) as i32 - font_size - outline_size * 2;
+        d.draw_rectangle(badge_x - outline_size, badge_y - outline_size, badge_width, font_size + outline_size * 2, Color::VIOLET.alpha(color_fade));
+        d.draw_text(badge, badge_x, badge_y, font_size, Color::WHITE);
+    }
+
+    // quality badges, bottom right corner of the thumbnail
+    let mut badge_x = (x + img_w * scale) as i32 - outline_size * 2;
+    for (badge, _) in img.quality.flags().iter().rev() {
+        let badge_width = d.measure_text(badge, font_size) + outline_size * 2;
+        badge_x -= badge_width;
+        let badge_y = (y + img_h * scale) as i32 - font_size - outline_size * 2;
+        d.draw_rectangle(badge_x - outline_size, badge_y - outline_size, badge_width, font_size + outline_size * 2, Color::ORANGE.alpha(color_fade));
+        d.draw_text(badge, badge_x, badge_y, font_size, Color::BLACK);
+        badge_x -= outline_size * 2;
+    }
+}
+
+fn clear_selection(images: &mut [ImgData]) {
+    for img in images.iter_mut() {
+        img.selected = false;
+    }
+}
+
+// `from` and `to` are positions in the view
+fn select_range(images: &mut [ImgData], view: &[usize], from: i32, to: i32) {
+    let (start, end) = (from.min(to), from.max(to));
+    for (position, i) in view.iter().enumerate() {
+        images[*i].selected = start <= position as i32 && position as i32 <= end;
+    }
+}
+
+// moves that place the (sorted) `targets` one after the other, the first one ending at `position`
+fn move_to_position(targets: &[usize], position: usize, len: usize) -> Vec<Edit> {
+    // the photos moving forward are placed starting from the last one and the ones moving back
+    // starting from the first one, so that no move shifts the photos already in place
+    let forward = targets.iter().enumerate().filter(|(n, target)| **target < position + n).count();
+    let mut order = (0..len).collect::<Vec<_>>();
+    let mut edits = vec![];
+    for n in (0..forward).rev().chain(forward..targets.len()) {
+        let from = order.iter().position(|i| *i == targets[n]).unwrap_or_default();
+        let to = position + n;
+        if from != to {
+            let moved = order.remove(from);
+            order.insert(to, moved);
+            edits.push(Edit::Move(from, to));
+        }
+    }
+    edits
+}
+
+// indices in `images` of the photos shown in the list
+fn filtered_view(images: &[ImgData], filter: ListFilter, min_rating: u8) -> Vec<usize> {
+    images.iter()
+        .enumerate()
+        .filter(|(_, img)| filter.accepts(img, min_rating))
+        .map(|(i, _)| i)
+        .collect()
+}
+
 #[derive(Debug, EnumIter, Eq, PartialEq, Copy, Clone)]
 enum AppTab {
     InputData,
@@ -149,6 +670,8 @@ impl fmt::Display for AppTab {
 enum UploadStatus {
     None,
     CreatingDir,
+    // photos taken outside of the dates of the activity, saved only after a confirmation
+    OutsideDates(String),
     SavingImage(usize),
     DoneSaving,
     Connecting,
@@ -219,6 +742,9 @@ fn gui_app() {
     rl.set_window_min_size(640, 480);
 
     let version_text = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let config = Config::load();
+    cache::clean(config.preview_cache);
+    let ui_font = UiFont::load(&mut rl, &thread);
 
     let mut app_tab = AppTab::InputData;
     let mut next_tab = app_tab;
@@ -235,69 +761,108 @@ fn gui_app() {
     let mut file_list_scroll_index = 0;
     let mut file_list_active: i32 = 0;
     let mut list_moved_by_key = false;
-
-    let mut titolo_buf = Vec::new();
-    let mut branca_buf = Vec::new();
-    let mut giorno_buf = Vec::new();
-    let mut mese_buf = Vec::new();
-    let mut anno_buf = Vec::new();
-    let mut server_buf = Vec::new();
-    let mut utente_buf = Vec::new();
-    let mut pw_buf = Vec::new();
+    let mut list_filter = ListFilter::All;
+    let mut min_rating = 0;
+    let mut history = History::default();
+    let mut selection_anchor = 0;
+    let mut move_position_buf: Option<Vec<u8>> = None;
+    let mut list_drag: Option<ListDrag> = None;
+    // photo dropped in the list (and position in the view) while drawing it, moved in the next update
+    let mut list_drop: Option<(usize, i32)> = None;
+    let mut last_autoscroll_time = 0.0;
+    let mut grid_view = false;
+    let mut grid_thumb_size = DEFAULT_GRID_THUMB_SIZE;
+    let mut grid_columns = 1;
+    let mut grid_scroll_row = 0;
+    let mut viewer: Option<Viewer> = None;
+    let mut compare: Option<Compare> = None;
+    let mut redact_tool: Option<RedactTool> = None;
+    // where the active photo was last drawn in the list view
+    let mut preview_rect = rrect(0.0, 0.0, 0.0, 0.0);
+    let mut frame: u64 = 0;
+
+    let mut titolo_buf = TextInput::new(32);
+    let branche_names = config.branche.iter().map(|branca| branca.name.clone()).collect::<Vec<_>>();
+    let mut branca_dropdown = Dropdown::default();
+    // events of the last imported calendar, while one of them is being chosen
+    let mut event_picker: Option<(Vec<Event>, Picker)> = None;
+    let mut import_error: Option<String> = None;
+    let mut date_buf = DateInput::new(Some(Local::now().date_naive()));
+    let mut end_date_buf = DateInput::new(None);
+    let mut server_buf = TextInput::new(32);
+    let mut utente_buf = TextInput::new(32);
+    let mut pw_buf = TextInput::secret(32);
 
     let mut text_box_width;
     let mut text_box_height;
 
     let mut titolo_rect = rrect(0.0, 0.0, 0.0, 0. );
     let mut branca_rect = rrect(0.0, 0.0, 0.0, 0. );
-    let mut giorno_rect = rrect(0.0, 0.0, 0.0, 0. );
-    let mut mese_rect = rrect(0.0, 0.0, 0.0, 0. );
-    let mut anno_rect = rrect(0.0, 0.0, 0.0, 0. );
+    let mut date_rect = rrect(0.0, 0.0, 0.0, 0. );
+    let mut end_date_rect = rrect(0.0, 0.0, 0.0, 0. );
     let mut server_rect = rrect(0.0, 0.0, 0.0, 0. );
     let mut utente_rect = rrect(0.0, 0.0, 0.0, 0. );
     let mut pw_rect = rrect(0.0, 0.0, 0.0, 0. );
     let mut hd_rect = rrect(0.0, 0.0, 0.0, 0. );
+    let mut picks_rect = rrect(0.0, 0.0, 0.0, 0. );
 
     let mut text_box_active = -1;
 
-    let mut titolo = String::default();
-    let mut branca = String::default();
-    let mut data = String::default();
-    let mut mese = 0;
-    let mut anno = 2000;
+    let naming = Naming::new(&config.folder_template, &config.file_template);
+    if let Err(e) = &naming {
+        eprintln!("[ERROR]: Modello dei nomi non valido in `fototpm-config.json`: {}", e);
+    }
+    let mut activity = Activity::default();
+    let mut metadata = Metadata::default();
     let mut server = String::default();
     let mut utente = String::default();
     let mut password = String::default();
 
     let mut hd_images = false;
+    let mut picks_only = false;
 
     let mut image_dir = String::default();
 
     let mut ftp_stream = None;
     let mut files_to_upload = Vec::new();
+    let mut images_to_save = Vec::new();
+    // private photos among the ones to save, saved or not depending on the settings
+    let mut private_count = 0;
 
     while !rl.window_should_close() {
-        let new_files = check_images_paths(&rl.load_dropped_files().paths());
+        frame += 1;
+        let dropped_files = rl.load_dropped_files();
+        let (calendar_paths, image_paths): (Vec<&str>, Vec<&str>) = dropped_files.paths().into_iter().partition(|path| is_calendar_path(path));
+        let new_files = check_images_paths(&image_paths);
         file_queue.append(&mut new_files.into());
 
+        if let Some(path) = calendar_paths.last().filter(|_| !upload) {
+            match ical::load(Path::new(path)) {
+                Ok(events) => {
+                    // most likely the photos are of the last activity that already started
+                    let today = Local::now().date_naive();
+                    let selected = events.iter().rposition(|event| event.start <= today).unwrap_or(0);
+                    let picker = Picker::new(events.iter().map(event_line).collect(), selected);
+                    println!("[INFO]: Imported {} events from `{}`.", events.len(), path);
+                    event_picker = Some((events, picker));
+                    import_error = None;
+                },
+                Err(e) => {
+                    eprintln!("[ERROR]: {}", e);
+                    import_error = Some(e);
+                },
+            }
+            next_tab = AppTab::InputData;
+        }
+
         if !upload {
             app_tab = next_tab;
 
             // Update
             match app_tab {
                 AppTab::InputData => {
-                    if check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_TAB)) {
-                        next_tab = get_next_tab(app_tab);
-                    } else if rl.is_key_pressed(KeyboardKey::KEY_TAB) || is_key_pressed_repeat(KeyboardKey::KEY_TAB)  {
-                        let delta = if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
-                            -1
-                        } else {
-                            1
-                        };
-                        text_box_active = (9 + text_box_active + delta) % 9;
-                    }
-                    
-                    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                    let popup_open = event_picker.is_some() || branca_dropdown.is_open() || date_buf.is_calendar_open() || end_date_buf.is_calendar_open();
+                    if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) && !popup_open {
                         text_box_active = -1;
                     }
 
@@ -310,9 +875,8 @@ fn gui_app() {
                     y += step;
                     branca_rect = rrect((w as f32 - text_box_width)/2.0, y, text_box_width, text_box_height );
                     y += step;
-                    giorno_rect = rrect((w as f32 - text_box_width * 4.0/4.0)/2.0, y, text_box_width/4.0, text_box_height );
-                    mese_rect   = rrect((w as f32 - text_box_width/4.0)/2.0, y, text_box_width/4.0, text_box_height );
-                    anno_rect   = rrect((w as f32 - text_box_width*-2.0/4.0)/2.0, y, text_box_width/4.0, text_box_height );
+                    date_rect   = rrect((w as f32 - text_box_width)/2.0, y, text_box_width * 0.45, text_box_height );
+                    end_date_rect = rrect((w as f32 + text_box_width)/2.0 - text_box_width * 0.45, y, text_box_width * 0.45, text_box_height );
                     y += step;
                     server_rect = rrect((w as f32 - text_box_width)/2.0, y, text_box_width, text_box_height );
                     y += step;
@@ -322,16 +886,46 @@ fn gui_app() {
 
                     y += step;
                     hd_rect     = rrect((w as f32 - text_box_width)/2.0 + text_box_height*0.3, y + text_box_height*0.3, text_box_height * 0.4, text_box_height * 0.4 );
+                    picks_rect  = rrect(w as f32/2.0 + text_box_height*0.3, y + text_box_height*0.3, text_box_height * 0.4, text_box_height * 0.4 );
+
+                    // the open list or calendar covers the other fields
+                    if let Some((_, picker)) = &mut event_picker {
+                        match gui_picker_update(&mut rl, picker, font_size) {
+                            Some(PickerAction::Pick(i)) => {
+                                if let Some((events, _)) = event_picker.take() {
+                                    apply_event(&events[i], &mut titolo_buf, &mut branca_dropdown, &mut date_buf, &mut end_date_buf, &config);
+                                }
+                            },
+                            Some(PickerAction::Close) => event_picker = None,
+                            None => {},
+                        }
+                    } else if popup_open {
+                        gui_dropdown_list_update(&mut rl, &mut branca_dropdown, &branche_names, font_size, branca_rect);
+                        gui_calendar_update(&mut rl, &mut date_buf, font_size, date_rect);
+                        gui_calendar_update(&mut rl, &mut end_date_buf, font_size, end_date_rect);
+                    } else {
+                        gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut titolo_buf, &ui_font, font_size, titolo_rect);
+                        gui_dropdown_update(&mut rl, &mut idx, &mut text_box_active, &mut branca_dropdown, &branche_names, branca_rect);
+                        gui_date_input_update(&mut rl, &mut idx, &mut text_box_active, &mut date_buf, &ui_font, font_size, date_rect);
+                        gui_date_input_update(&mut rl, &mut idx, &mut text_box_active, &mut end_date_buf, &ui_font, font_size, end_date_rect);
+                        gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut server_buf, &ui_font, font_size, server_rect);
+                        gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut utente_buf, &ui_font, font_size, utente_rect);
+                        gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut pw_buf, &ui_font, font_size, pw_rect);
+                        gui_check_box_update(&mut rl, &mut idx, &mut text_box_active, hd_rect, &mut hd_images);
+                        gui_check_box_update(&mut rl, &mut idx, &mut text_box_active, picks_rect, &mut picks_only);
 
-                    gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut titolo_buf, 32, titolo_rect);
-                    gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut branca_buf, 8, branca_rect);
-                    gui_number_input_update(&mut rl, &mut idx, &mut text_box_active, &mut giorno_buf, 2, giorno_rect);
-                    gui_number_input_update(&mut rl, &mut idx, &mut text_box_active, &mut mese_buf, 2, mese_rect);
-                    gui_number_input_update(&mut rl, &mut idx, &mut text_box_active, &mut anno_buf, 2, anno_rect);
-                    gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut server_buf, 32, server_rect);
-                    gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut utente_buf, 32, utente_rect);
-                    gui_text_input_update(&mut rl, &mut idx, &mut text_box_active, &mut pw_buf, 32, pw_rect);
-                    gui_check_box_update(&mut rl, &mut idx, &mut text_box_active, hd_rect, &mut hd_images);
+                        if check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_TAB)) {
+                            next_tab = get_next_tab(app_tab);
+                        } else if rl.is_key_pressed(KeyboardKey::KEY_TAB) || is_key_pressed_repeat(KeyboardKey::KEY_TAB)  {
+                            let delta = if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                                -1
+                            } else {
+                                1
+                            };
+                            // `idx` is now the number of widgets
+                            text_box_active = (idx + text_box_active + delta) % idx;
+                        }
+                    }
                 },
                 AppTab::SelectionLab => {
                     if last_image_loaded {
@@ -339,62 +933,325 @@ fn gui_app() {
                         last_image_loaded = false;
                     }
 
-                    if let Some(path) = file_queue.pop_front() {
+                    if let Some((dragged_image, drop_position)) = list_drop.take() {
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        let position = drop_images(&mut history, &mut images, &view, dragged_image, drop_position, file_list_active);
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        if let Some(active) = view.iter().position(|i| *i == position) {
+                            file_list_active = active as i32;
+                            selection_anchor = file_list_active;
+                        }
+                    }
+
+                    if let Some((path, meta)) = file_queue.pop_front() {
                         if file_queue.len() == 0 {
                             last_image_loaded = true;
                         }
                         
                         println!("[INFO]: Loading image: `{}`...", path.display());
-                        if let Ok(img_) = ImageReader::open(path.clone()) {
-                            if let Ok(img) = img_.decode() {
-                                let img_scaled;
-                                let size = img.dimensions();
+                        let small_dim = if hd_images { HD_SMALLER_DIMENSION } else { SMALLER_DIMENSION };
+                        let big_dim = if hd_images { HD_BIGGER_DIMENSION } else { BIGGER_DIMENSION };
 
-                                let small_dim = if hd_images { HD_SMALLER_DIMENSION } else { SMALLER_DIMENSION };
-                                let big_dim = if hd_images { HD_BIGGER_DIMENSION } else { BIGGER_DIMENSION };
+                        if let Some(preview) = cache::load_preview(&path, small_dim, big_dim) {
+                            let quality = Quality::analyze(preview.original_size, &preview.image, small_dim, big_dim);
+                            let filename = path.file_name().unwrap_or_default().to_str().unwrap_or_default().to_owned();
 
-                                if size.0 > size.1 {
-                                    img_scaled = img.resize_to_fill(big_dim, small_dim, Lanczos3);
-                                } else {
-                                    img_scaled = img.resize_to_fill(small_dim, big_dim, Lanczos3);
+                            images.push(ImgData::new(path.canonicalize().unwrap_or(path), filename, preview, quality, meta, frame));
+                        }
+                    } else if let Some(v) = &mut viewer {
+                        let bounds = rrect(0.0, 0.0, w as f32, h as f32);
+                        let image_size = shown_size(&v.texture, &images[v.image]);
+                        v.zoom_pan.update(&rl, image_size, bounds);
+                        if rl.is_key_pressed(KeyboardKey::KEY_Z) {
+                            v.zoom_pan.toggle_actual_size(image_size, bounds);
+                        }
+
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        let position = view.iter().position(|i| *i == v.image).unwrap_or(0) as i32;
+                        let mut next_position = position;
+                        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) || is_key_pressed_repeat(KeyboardKey::KEY_LEFT) || rl.is_key_pressed(KeyboardKey::KEY_UP) || is_key_pressed_repeat(KeyboardKey::KEY_UP) {
+                            next_position -= 1;
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || is_key_pressed_repeat(KeyboardKey::KEY_RIGHT) || rl.is_key_pressed(KeyboardKey::KEY_DOWN) || is_key_pressed_repeat(KeyboardKey::KEY_DOWN) {
+                            next_position += 1;
+                        }
+                        let next_position = next_position.min(view.len() as i32 - 1).max(0);
+                        if next_position != position {
+                            let next_image = view[next_position as usize];
+                            *v = Viewer::open(&mut rl, &thread, next_image, &images[next_image].path, images[next_image].meta.rotation, v.zoom_pan);
+                            file_list_active = next_position;
+                            list_moved_by_key = true;
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+                            viewer = None;
+                        }
+                    } else if let Some(c) = &mut compare {
+                        let panels = c.panels(rrect(0.0, 0.0, w as f32, (h - font_size * 2) as f32));
+                        let mouse = rl.get_mouse_position();
+                        for (n, bounds) in panels.iter().enumerate() {
+                            let image_size = shown_size(&c.textures[n], &images[c.images[n]]);
+                            c.zoom_pan.update(&rl, image_size, *bounds);
+                            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && unsafe { CheckCollisionPointRec(mouse.into(), (*bounds).into()) } {
+                                c.active = n;
+                            }
+                        }
+
+                        let panel_count = c.images.len();
+                        for (n, key) in RATING_KEYS[1..].iter().enumerate().take(panel_count) {
+                            if rl.is_key_pressed(*key) {
+                                c.active = n;
+                            }
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) || is_key_pressed_repeat(KeyboardKey::KEY_LEFT) {
+                            c.active = (c.active + panel_count - 1) % panel_count;
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || is_key_pressed_repeat(KeyboardKey::KEY_RIGHT) {
+                            c.active = (c.active + 1) % panel_count;
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_Z) {
+                            let image_size = shown_size(&c.textures[c.active], &images[c.images[c.active]]);
+                            c.zoom_pan.toggle_actual_size(image_size, panels[c.active]);
+                        }
+
+                        let active_image = c.images[c.active];
+                        if rl.is_key_pressed(KeyboardKey::KEY_X) {
+                            let mut meta = images[active_image].meta.clone();
+                            meta.flag = if meta.flag == PickFlag::Reject { PickFlag::Unflagged } else { PickFlag::Reject };
+                            history.apply(Edit::SetMeta(active_image, meta), &mut images);
+                        }
+
+                        let mut close = rl.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+                        if rl.is_key_pressed(KeyboardKey::KEY_P) || rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                            // keeping the active photo and rejecting the others
+                            let edits = c.images.iter()
+                                .filter_map(|i| {
+                                    let mut meta = images[*i].meta.clone();
+                                    meta.flag = if *i == active_image { PickFlag::Pick } else { PickFlag::Reject };
+                                    if meta != images[*i].meta {
+                                        Some(Edit::SetMeta(*i, meta))
+                                    } else {
+                                        None
+                                    }
+                                })
+                                .collect();
+                            history.apply(Edit::Batch(edits), &mut images);
+                            clear_selection(&mut images);
+                            if let Some(position) = filtered_view(&images, list_filter, min_rating).iter().position(|i| *i == active_image) {
+                                file_list_active = position as i32;
+                                selection_anchor = file_list_active;
+                                list_moved_by_key = true;
+                            }
+                            close = true;
+                        }
+
+                        if close {
+                            compare = None;
+                        }
+                    } else if let Some(tool) = &mut redact_tool {
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        let mouse = rl.get_mouse_position();
+                        let pointed = rvec2((mouse.x - preview_rect.x) / preview_rect.width, (mouse.y - preview_rect.y) / preview_rect.height);
+                        let on_photo = unsafe { CheckCollisionPointRec(mouse.into(), preview_rect.into()) };
+
+                        if let Some(&active_image) = view.get(file_list_active as usize) {
+                            let meta = images[active_image].meta.clone();
+                            let mut new_redactions = meta.redactions.clone();
+
+                            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && on_photo {
+                                tool.drag_start = Some(pointed);
+                            }
+                            if let Some(start) = tool.drag_start.filter(|_| rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT)) {
+                                let area = tool.area(start, pointed);
+                                if area.width >= MIN_REDACTION_SIZE && area.height >= MIN_REDACTION_SIZE {
+                                    new_redactions.push(area.unrotated(meta.rotation));
+                                }
+                                tool.drag_start = None;
+                            }
+
+                            // the area on top is the last one drawn
+                            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) && on_photo {
+                                if let Some(pointed_area) = meta.redactions.iter().rposition(|area| area.rotated(meta.rotation).contains(pointed.x, pointed.y)) {
+                                    new_redactions.remove(pointed_area);
                                 }
+                            }
+                            if rl.is_key_pressed(KeyboardKey::KEY_DELETE) || rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+                                new_redactions.pop();
+                            }
+
+                            if new_redactions != meta.redactions {
+                                history.apply(Edit::SetMeta(active_image, ImgMeta { redactions: new_redactions, ..meta }), &mut images);
+                            }
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_E) {
+                            tool.shape = tool.shape.toggled();
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_B) {
+                            tool.effect = tool.effect.toggled();
+                        }
+
+                        let prev_file_list_active = file_list_active;
+                        if rl.is_key_pressed(KeyboardKey::KEY_UP) || is_key_pressed_repeat(KeyboardKey::KEY_UP) {
+                            file_list_active -= 1;
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) || is_key_pressed_repeat(KeyboardKey::KEY_DOWN) {
+                            file_list_active += 1;
+                        }
+                        file_list_active = file_list_active.min(view.len() as i32 - 1).max(0);
+                        if file_list_active != prev_file_list_active {
+                            tool.drag_start = None;
+                            clear_selection(&mut images);
+                            selection_anchor = file_list_active;
+                            list_moved_by_key = true;
+                        }
 
-                                let bytes_ = img_scaled.to_rgb8();
-                                let mut bytes = bytes_.as_raw().to_owned();
-                                
-                                let rimg = unsafe {
-                                    Image::from_raw(raylib::ffi::Image {
-                                        data: bytes.as_mut_ptr() as *mut c_void,
-                                        width: img_scaled.width() as i32,
-                                        height: img_scaled.height() as i32,
-                                        mipmaps: 1,
-                                        format: PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8 as i32
-                                    })
-                                };
-                                
-                                // not eliminating unwrap because do not want to mess with mem::forget
-                                // should work fine anyway...
-                                let texture = rl.load_texture_from_image(&thread, &rimg).unwrap();
-                                std::mem::forget(rimg);
-                                let filename = path.file_name().unwrap_or_default().to_str().unwrap_or_default().to_owned();
-
-                                images.push(ImgData::new(path.canonicalize().unwrap_or(path), filename, img_scaled, texture));
+                        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || rl.is_key_pressed(KeyboardKey::KEY_O) {
+                            redact_tool = None;
+                        }
+                    } else if let Some(position_buf) = &mut move_position_buf {
+                        // typing the position where the selected photos are moved
+                        while let Some(c) = rl.get_char_pressed() {
+                            if c.is_ascii_digit() && position_buf.len() < 5 {
+                                position_buf.push(c as u8);
                             }
                         }
+                        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE) {
+                            position_buf.pop();
+                        }
+
+                        let position = String::from_utf8(position_buf.clone()).unwrap_or_default().parse::<usize>().ok();
+                        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                            move_position_buf = None;
+                        } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_KP_ENTER) {
+                            if let Some(position) = position {
+                                let view = filtered_view(&images, list_filter, min_rating);
+                                let targets = operation_targets(&images, &view, file_list_active);
+                                let position = position.min(images.len() - targets.len() + 1).max(1) - 1;
+                                history.apply(Edit::Batch(move_to_position(&targets, position, images.len())), &mut images);
+
+                                let view = filtered_view(&images, list_filter, min_rating);
+                                if let Some(active) = view.iter().position(|i| *i == position) {
+                                    file_list_active = active as i32;
+                                    selection_anchor = file_list_active;
+                                }
+                                list_moved_by_key = true;
+                            }
+                            move_position_buf = None;
+                        }
                     } else {
                         if check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_TAB)) {
                             next_tab = get_next_tab(app_tab);
                             text_box_active = -1;
                         }
 
-                        if rl.is_key_pressed(KeyboardKey::KEY_DELETE) {
-                            images.remove(file_list_active as usize);
+                        let prev_filter = (list_filter, min_rating);
+                        if rl.is_key_pressed(KeyboardKey::KEY_F) {
+                            list_filter = list_filter.next();
+                        }
+                        if check_ctrl_shortcut(&rl, None) {
+                            for (rating, key) in RATING_KEYS.iter().enumerate() {
+                                if rl.is_key_pressed(*key) {
+                                    min_rating = rating as u8;
+                                }
+                            }
+                        }
+                        if (list_filter, min_rating) != prev_filter {
+                            let view = filtered_view(&images, prev_filter.0, prev_filter.1);
+                            let active_image = view.get(file_list_active as usize).copied();
+                            let view = filtered_view(&images, list_filter, min_rating);
+                            file_list_active = active_image.and_then(|a| view.iter().position(|i| *i == a)).unwrap_or(0) as i32;
+                            file_list_scroll_index = 0;
+                            selection_anchor = file_list_active;
+                            list_moved_by_key = true;
+                        }
+
+                        let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                        let undo_pressed = check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_Z)) && !shift_down;
+                        let redo_pressed = check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_Y)) || (check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_Z)) && shift_down);
+                        let touched_image = if undo_pressed {
+                            history.undo(&mut images)
+                        } else if redo_pressed {
+                            history.redo(&mut images)
+                        } else {
+                            None
+                        };
+                        if let Some(touched_image) = touched_image {
+                            let view = filtered_view(&images, list_filter, min_rating);
+                            if let Some(position) = view.iter().position(|i| *i == touched_image) {
+                                file_list_active = position as i32;
+                            }
+                            list_moved_by_key = true;
+                        }
+
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        let alt_down = rl.is_key_down(KeyboardKey::KEY_LEFT_ALT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+
+                        if check_ctrl_shortcut(&rl, Some(KeyboardKey::KEY_A)) {
+                            for i in view.iter() {
+                                images[*i].selected = true;
+                            }
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                            clear_selection(&mut images);
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_DELETE) && !view.is_empty() {
+                            let targets = operation_targets(&images, &view, file_list_active);
+                            // removing from the back so that the indices of the others stay valid
+                            let edits = targets.into_iter().rev().map(Edit::Remove).collect();
+                            history.apply(Edit::Batch(edits), &mut images);
+                            clear_selection(&mut images);
                             list_moved_by_key = true;
                         }
 
+                        if rl.is_key_pressed(KeyboardKey::KEY_M) && !view.is_empty() {
+                            move_position_buf = Some(Vec::new());
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_O) && !view.is_empty() {
+                            // the areas are drawn on the large preview of the list
+                            grid_view = false;
+                            list_drag = None;
+                            redact_tool = Some(RedactTool::default());
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) || rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                            if let Some(&active_image) = view.get(file_list_active as usize) {
+                                viewer = Some(Viewer::open(&mut rl, &thread, active_image, &images[active_image].path, images[active_image].meta.rotation, ZoomPan::default()));
+                            }
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_C) {
+                            let selected = view.iter().copied().filter(|i| images[*i].selected).collect::<Vec<_>>();
+                            if (Compare::MIN_IMAGES..=Compare::MAX_IMAGES).contains(&selected.len()) {
+                                compare = Some(Compare::open(&mut rl, &thread, selected, &images));
+                            } else {
+                                eprintln!("[INFO]: Per confrontare le foto bisogna selezionarne da {} a {}.", Compare::MIN_IMAGES, Compare::MAX_IMAGES);
+                            }
+                        }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_G) {
+                            grid_view = !grid_view;
+                            list_drag = None;
+                            list_moved_by_key = true;
+                        }
+                        if grid_view {
+                            if rl.is_key_pressed(KeyboardKey::KEY_EQUAL) || rl.is_key_pressed(KeyboardKey::KEY_KP_ADD) {
+                                grid_thumb_size = (grid_thumb_size + GRID_THUMB_SIZE_STEP).min(MAX_GRID_THUMB_SIZE);
+                            }
+                            if rl.is_key_pressed(KeyboardKey::KEY_MINUS) || rl.is_key_pressed(KeyboardKey::KEY_KP_SUBTRACT) {
+                                grid_thumb_size = (grid_thumb_size - GRID_THUMB_SIZE_STEP).max(MIN_GRID_THUMB_SIZE);
+                            }
+                        }
+
+                        let view = filtered_view(&images, list_filter, min_rating);
                         let prev_file_list_active = file_list_active;
 
-                        let fast_step = (images.len() as f32 / 10.0).ceil() as i32;
+                        let fast_step = (view.len() as f32 / 10.0).ceil() as i32;
+                        // in the grid up and down move by a whole row
+                        let step = if grid_view { grid_columns } else { 1 };
                         if check_ctrl_shortcut(&rl, None) {
                             if rl.is_key_pressed(KeyboardKey::KEY_UP) || is_key_pressed_repeat(KeyboardKey::KEY_UP) {
                                 file_list_active -= fast_step;
@@ -406,81 +1263,156 @@ fn gui_app() {
                             }
                         } else {
                             if rl.is_key_pressed(KeyboardKey::KEY_UP) || is_key_pressed_repeat(KeyboardKey::KEY_UP) {
-                                file_list_active -= 1;
+                                file_list_active -= step;
                                 list_moved_by_key = true;
                             }
                             if rl.is_key_pressed(KeyboardKey::KEY_DOWN) || is_key_pressed_repeat(KeyboardKey::KEY_DOWN) {
+                                file_list_active += step;
+                                list_moved_by_key = true;
+                            }
+                            if grid_view && (rl.is_key_pressed(KeyboardKey::KEY_LEFT) || is_key_pressed_repeat(KeyboardKey::KEY_LEFT)) {
+                                file_list_active -= 1;
+                                list_moved_by_key = true;
+                            }
+                            if grid_view && (rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || is_key_pressed_repeat(KeyboardKey::KEY_RIGHT)) {
                                 file_list_active += 1;
                                 list_moved_by_key = true;
                             }
                         }
 
-                        file_list_active = file_list_active.min(images.len() as i32 - 1).max(0);
+                        file_list_active = file_list_active.min(view.len() as i32 - 1).max(0);
 
-                        if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
-                            if (file_list_active - prev_file_list_active).abs() <= 1 {
-                                images.swap(prev_file_list_active as usize, file_list_active as usize);
+                        if !view.is_empty() && file_list_active != prev_file_list_active {
+                            if alt_down {
+                                let from = view[prev_file_list_active.min(view.len() as i32 - 1).max(0) as usize];
+                                let to = view[file_list_active as usize];
+                                if (file_list_active - prev_file_list_active).abs() <= 1 {
+                                    history.apply(Edit::Swap(from, to), &mut images);
+                                } else {
+                                    history.apply(Edit::Move(from, to), &mut images);
+                                }
+                                selection_anchor = file_list_active;
+                            } else if shift_down {
+                                select_range(&mut images, &view, selection_anchor, file_list_active);
                             } else {
-                                let img_to_move = images.remove(prev_file_list_active as usize);
-                                images.insert(file_list_active as usize, img_to_move);
+                                clear_selection(&mut images);
+                                selection_anchor = file_list_active;
                             }
                         }
 
+                        if rl.is_key_pressed(KeyboardKey::KEY_R) && !view.is_empty() {
+                            let edits = operation_targets(&images, &view, file_list_active).into_iter()
+                                .map(|i| Edit::Rotate(i, !shift_down))
+                                .collect();
+                            history.apply(Edit::Batch(edits), &mut images);
+                        }
 
-                        if rl.is_key_pressed(KeyboardKey::KEY_R) {
-                            if file_list_active >= 0 && !images.is_empty() {
-                                let rotated_image = if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
-                                    images[file_list_active as usize].image.rotate270()
-                                } else {
-                                    images[file_list_active as usize].image.rotate90()
-                                };
-
-                                let bytes_ = rotated_image.to_rgb8();
-                                let mut bytes = bytes_.as_raw().to_owned();
-                                
-                                let rimg = unsafe{
-                                    Image::from_raw(raylib::ffi::Image {
-                                        data: bytes.as_mut_ptr() as *mut c_void,
-                                        width: rotated_image.width() as i32,
-                                        height: rotated_image.height() as i32,
-                                        mipmaps: 1,
-                                        format: PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8 as i32
-                                    })
-                                };
-                                
-                                // not eliminating unwrap because do not want to mess with mem::forget
-                                // should work fine anyway...
-                                let texture = rl.load_texture_from_image(&thread, &rimg).unwrap();
-                                std::mem::forget(rimg);
-                                images[file_list_active as usize].image = rotated_image;
-                                images[file_list_active as usize].texture = texture;
+                        let targets = operation_targets(&images, &view, file_list_active);
+                        let mut new_private = None;
+                        if rl.is_key_pressed(KeyboardKey::KEY_N) {
+                            new_private = Some(!targets.iter().all(|i| images[*i].meta.private));
+                        }
+                        let mut new_rating = None;
+                        let mut new_flag = None;
+                        if !check_ctrl_shortcut(&rl, None) {
+                            for (rating, key) in RATING_KEYS.iter().enumerate() {
+                                if rl.is_key_pressed(*key) {
+                                    new_rating = Some(rating as u8);
+                                }
                             }
                         }
+
+                        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+                            let all_picked = targets.iter().all(|i| images[*i].meta.flag == PickFlag::Pick);
+                            new_flag = Some(if all_picked { PickFlag::Unflagged } else { PickFlag::Pick });
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_X) {
+                            let all_rejected = targets.iter().all(|i| images[*i].meta.flag == PickFlag::Reject);
+                            new_flag = Some(if all_rejected { PickFlag::Unflagged } else { PickFlag::Reject });
+                        }
+                        if rl.is_key_pressed(KeyboardKey::KEY_U) {
+                            new_flag = Some(PickFlag::Unflagged);
+                        }
+
+                        if new_rating.is_some() || new_flag.is_some() || new_private.is_some() {
+                            let edits = targets.iter()
+                                .filter_map(|i| {
+                                    let mut meta = images[*i].meta.clone();
+                                    meta.rating = new_rating.unwrap_or(meta.rating);
+                                    meta.flag = new_flag.unwrap_or(meta.flag);
+                                    meta.private = new_private.unwrap_or(meta.private);
+                                    if meta != images[*i].meta {
+                                        Some(Edit::SetMeta(*i, meta))
+                                    } else {
+                                        None
+                                    }
+                                })
+                                .collect();
+                            history.apply(Edit::Batch(edits), &mut images);
+                        }
                     }
                 }
             };
         } else {
             match upload_status {
                 UploadStatus::None => {
-                    titolo = clean_string(String::from_utf8(titolo_buf.clone()).unwrap_or_default());
-                    branca = clean_string(String::from_utf8(branca_buf.clone()).unwrap_or_default()).to_uppercase();
-                    
-                    data = format!("{:0>2}{:0>2}{:0>2}",String::from_utf8(anno_buf.clone()).unwrap_or_default(), String::from_utf8(mese_buf.clone()).unwrap_or_default(), String::from_utf8(giorno_buf.clone()).unwrap_or_default());
-
-                    anno = 2000 + String::from_utf8(anno_buf.clone()).unwrap_or_default().parse::<usize>().unwrap_or(0);
-                    mese = String::from_utf8(mese_buf.clone()).unwrap_or_default().parse::<usize>().unwrap_or(0);
+                    let new_activity = activity_from_inputs(&titolo_buf, &branca_dropdown, &date_buf, &end_date_buf, &config);
+                    if let Ok(new_activity) = &new_activity {
+                        activity = new_activity.clone();
+                    }
 
-                    server = String::from_utf8(server_buf.clone()).unwrap_or_default();
-                    utente = String::from_utf8(utente_buf.clone()).unwrap_or_default();
-                    password = String::from_utf8(pw_buf.clone()).unwrap_or_default();
+                    server = server_buf.as_str().to_owned();
+                    utente = utente_buf.as_str().to_owned();
+                    password = pw_buf.as_str().to_owned();
+
+                    let to_save = photos_to_save(&images, picks_only);
+                    let outside_dates = photos_outside_dates(&images, &to_save, &activity);
+                    // the private photos are numbered on their own, so that the uploaded ones have no gaps
+                    let (private, public): (Vec<usize>, Vec<usize>) = to_save.iter().partition(|i| images[**i].meta.private);
+                    private_count = private.len();
+
+                    let names = naming.clone().and_then(|naming| {
+                        new_activity?;
+                        let dir = naming.folder(&activity)?;
+                        let mut files = public.iter()
+                            .enumerate()
+                            .map(|(n, i)| (*i, naming.file(&activity, &photo_info(&images[*i], n + 1, &config))))
+                            .collect::<Vec<_>>();
+                        if let Some(private_folder) = &config.private_folder {
+                            files.extend(private.iter()
+                                .enumerate()
+                                .map(|(n, i)| (*i, format!("{}/{}", private_folder, naming.file(&activity, &photo_info(&images[*i], n + 1, &config))))));
+                        }
+                        Ok((dir, files))
+                    });
 
-                    image_dir = format!("{}_{}_{}", data, branca, titolo);
-                    // println!("{}", image_dir);
-                    upload_status = UploadStatus::CreatingDir;
+                    upload_status = if let Err(e) = names {
+                        UploadStatus::Error(e)
+                    } else if to_save.is_empty() {
+                        if picks_only {
+                            UploadStatus::Error(String::from("Nessuna foto è stata scelta (opzione `Solo foto scelte`)."))
+                        } else {
+                            UploadStatus::Error(String::from("Tutte le foto sono state scartate."))
+                        }
+                    } else if public.is_empty() && config.private_folder.is_none() {
+                        UploadStatus::Error(String::from("Tutte le foto sono private e non vengono salvate (impostazione `cartella_private`)."))
+                    } else {
+                        (image_dir, images_to_save) = names.unwrap_or_default();
+                        metadata = activity_metadata(&titolo_buf, &branca_dropdown, &activity, &config);
+                        // a camera with the wrong clock or a photo taken after midnight should not block the upload
+                        match outside_dates.first() {
+                            Some((i, taken)) => UploadStatus::OutsideDates(format!("{} foto sono state scattate fuori dalle date dell'attività (ad esempio `{}`, del {}).\nSe non è voluto, correggere le date nella scheda `{}` o scartare le foto.", outside_dates.len(), images[*i].filename, format_date(*taken), AppTab::InputData)),
+                            None => UploadStatus::CreatingDir,
+                        }
+                    };
                 },
                 UploadStatus::CreatingDir => {
                     let _ = fs::remove_dir_all(image_dir.clone());
-                    upload_status = match fs::create_dir(image_dir.clone()) {
+                    let created = fs::create_dir(image_dir.clone()).and_then(|_| match &config.private_folder {
+                        Some(private_folder) if private_count > 0 => fs::create_dir(format!("{}/{}", image_dir, private_folder)),
+                        _ => Ok(()),
+                    });
+                    upload_status = match created {
                         Ok(_) => UploadStatus::SavingImage(0),
                         Err(e) => {
                             eprintln!("[ERROR]: Impossibile creare la cartella `{}`: {}", image_dir, e);
@@ -489,16 +1421,39 @@ fn gui_app() {
                     };
                 },
                 UploadStatus::SavingImage(i) => {
-                    let new_name = format!("{}/{}_{}_{}_{:03}.JPG", image_dir, data, branca, titolo, i + 1);
+                    let (img_index, file_name) = &images_to_save[i];
+                    let new_name = format!("{}/{}", image_dir, file_name);
+
+                    let img = &mut images[*img_index];
+                    let photo_metadata = metadata.for_photo(&img.exif, config.privacy);
+                    let saved = match img.redacted_image() {
+                        Some(preview) => {
+                            // the previews are as big as the saved photos, so their size tells the profile of the watermark
+                            let preview = match &config.watermark {
+                                Some(watermark) => Cow::Owned(watermark.apply(&preview, preview.width().max(preview.height()) > BIGGER_DIMENSION)),
+                                None => preview,
+                            };
+                            metadata::save_jpeg(&preview, &new_name, config.write_metadata.then_some(&photo_metadata))
+                        },
+                        None => Err(image::ImageError::IoError(io::Error::new(io::ErrorKind::NotFound, format!("anteprima di `{}` non disponibile", img.path.display())))),
+                    };
 
-                    upload_status = match images[i].image.save(new_name) {
+                    upload_status = match saved {
                         Ok(_) => {
-                            if i+1 < images.len() {
+                            if i+1 < images_to_save.len() {
                                  UploadStatus::SavingImage(i+1)
                             } else {
                                 files_to_upload = find_files(&image_dir);
                                 files_to_upload.reverse();
-                                UploadStatus::DoneSaving
+                                // with the privacy mode the saved photos must not tell where they were taken
+                                let with_gps = if config.privacy { files_with_gps(&files_to_upload) } else { Vec::new() };
+                                if let Some(path) = with_gps.first() {
+                                    eprintln!("[ERROR]: {} foto in `{}` contengono la posizione GPS, ad esempio `{}`.", with_gps.len(), image_dir, path.display());
+                                    files_to_upload.clear();
+                                    UploadStatus::Error(format!("{} foto salvate contengono ancora la posizione GPS (ad esempio `{}`).\nIl caricamento è stato bloccato.", with_gps.len(), path.display()))
+                                } else {
+                                    UploadStatus::DoneSaving
+                                }
                             }
                         },
                         Err(e) => {
@@ -508,6 +1463,7 @@ fn gui_app() {
                     };
                     
                 },
+                UploadStatus::OutsideDates(_) => {},
                 UploadStatus::DoneSaving => {},
                 UploadStatus::Connecting => {
                     upload_status = match FtpStream::connect(format!("{}:21", server)) {
@@ -533,21 +1489,20 @@ fn gui_app() {
                     if let Some(stream) = &mut ftp_stream {
                         if i == 0 {
                             // println!("{:?}", stream.list(None).unwrap());
-                            let dir = if mese < 8 {
-                                format!("{}-{}", anno - 1, anno)
-                            } else {
-                                format!("{}-{}", anno, anno + 1)
+                            // activities of more days go in the season of their first day
+                            let season_dir = config.season.folder(activity.date);
+                            let cwd = match &season_dir {
+                                Some(dir) => stream.cwd(dir).map(|_| println!("[FTP]: cd {}/", dir)),
+                                None => Ok(()),
                             };
 
-                            if let Err(e) = stream.cwd(&dir) {
+                            if let Err(e) = cwd {
+                                let dir = season_dir.unwrap_or_default();
                                 eprintln!("[ERROR]: Sul server ftp `{}` non esiste la cartella `{}`: {}\nImpossibile caricare le immagini.", server, dir, e);
                                 upload_status = UploadStatus::Error(format!("Sul server ftp `{}` non esiste la cartella `{}`.\nImpossibile caricare le immagini.", server, dir));
-                            } else {
-                                println!("[FTP]: cd {}/", dir);
-                                if let Err(e) = stream.mkdir(&image_dir) {
-                                    eprintln!("[ERROR]: Sul server ftp `{}` esiste già la cartella `{}`: {}\nImpossibile caricare le immagini.", server, image_dir, e);
-                                    upload_status = UploadStatus::Error(format!("Sul server ftp `{}` esiste già la cartella `{}`.\nImpossibile caricare le immagini.", server, image_dir));
-                                }
+                            } else if let Err(e) = stream.mkdir(&image_dir) {
+                                eprintln!("[ERROR]: Sul server ftp `{}` esiste già la cartella `{}`: {}\nImpossibile caricare le immagini.", server, image_dir, e);
+                                upload_status = UploadStatus::Error(format!("Sul server ftp `{}` esiste già la cartella `{}`.\nImpossibile caricare le immagini.", server, image_dir));
                             }
                             // IDK, FTP error... at this point let it just crash
                             stream.transfer_type(ftp::types::FileType::Image).unwrap();
@@ -579,7 +1534,10 @@ fn gui_app() {
                 UploadStatus::Error(_) => {},
             };
         }
-        
+
+        // keeping what was drawn in the previous frame, it is likely to be drawn again
+        unload_previews(&mut images, config.preview_memory, frame - 1);
+
         let mut d = rl.begin_drawing(&thread);
         (w, h) = (d.get_screen_width(), d.get_screen_height());
         font_size = h/42;
@@ -603,14 +1561,14 @@ fn gui_app() {
                         next_tab = tab;
                     }
                     let mut idx = 0;
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Titolo dell'attività", &mut titolo_buf, font_size, titolo_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Branca", &mut branca_buf, font_size, branca_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Giorno", &mut giorno_buf, font_size, giorno_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Mese", &mut mese_buf, font_size, mese_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Anno", &mut anno_buf, font_size, anno_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Server", &mut server_buf, font_size, server_rect);
-                    gui_text_input(&mut d, &mut idx, text_box_active, "Utente", &mut utente_buf, font_size, utente_rect);
-                    gui_seecret_text_input(&mut d, &mut idx, text_box_active, "Password", &mut pw_buf, font_size, pw_rect);
+                    let field = |rect| Field { font: &ui_font, size: font_size, rect };
+                    gui_text_input(&mut d, &mut idx, text_box_active, "Titolo dell'attività", &mut titolo_buf, field(titolo_rect));
+                    gui_dropdown(&mut d, &mut idx, text_box_active, "Branca", &branca_dropdown, &branche_names, field(branca_rect));
+                    gui_date_input(&mut d, &mut idx, text_box_active, "Data (gg/mm/aaaa)", &mut date_buf, field(date_rect));
+                    gui_date_input(&mut d, &mut idx, text_box_active, "Fine (se più giorni)", &mut end_date_buf, field(end_date_rect));
+                    gui_text_input(&mut d, &mut idx, text_box_active, "Server", &mut server_buf, field(server_rect));
+                    gui_text_input(&mut d, &mut idx, text_box_active, "Utente", &mut utente_buf, field(utente_rect));
+                    gui_text_input(&mut d, &mut idx, text_box_active, "Password", &mut pw_buf, field(pw_rect));
 
                     // let hd_text = CString::new("HD (prima di caricare le foto)").unwrap();
 
@@ -625,6 +1583,41 @@ fn gui_app() {
                     d.draw_text(hd_text, (hd_rect.x + hd_rect.width * 2.0) as i32, (hd_rect.y + hd_rect.height) as i32 - font_size, font_size, hd_color);
                     let small_font_size = font_size * 3 / 4;
                     d.draw_text("(premere prima di importare le foto)", (hd_rect.x + hd_rect.width * 2.0) as i32 + hd_text_size, (hd_rect.y + hd_rect.height) as i32 - small_font_size, small_font_size, hd_color);
+
+                    gui_check_box(&mut d, &mut idx, text_box_active, picks_rect, picks_only);
+                    let picks_color = if picks_only { Color::WHITE } else { Color::GRAY };
+                    d.draw_text("Solo foto scelte", (picks_rect.x + picks_rect.width * 2.0) as i32, (picks_rect.y + picks_rect.height) as i32 - font_size, font_size, picks_color);
+
+                    // names that the saved folder and first photo would get now
+                    let activity = activity_from_inputs(&titolo_buf, &branca_dropdown, &date_buf, &end_date_buf, &config);
+                    let names_preview = naming.clone().and_then(|naming| {
+                        let activity = activity.clone()?;
+                        let photo = images.first()
+                            .map(|img| photo_info(img, 1, &config))
+                            .unwrap_or(Photo { number: 1, original: String::from("IMG_0001"), photographer: String::new() });
+                        Ok(format!("{}/{}", naming.folder(&activity)?, naming.file(&activity, &photo)))
+                    });
+                    let (names_text, names_color) = match names_preview {
+                        Ok(names) => (format!("Nome: {}", names), Color::GRAY),
+                        Err(e) => (e, Color::RED),
+                    };
+                    let small_font_size = font_size * 3 / 4;
+                    let names_text_width = ui_font.measure(&names_text, small_font_size);
+                    ui_font.draw(&mut d, &names_text, (w - names_text_width)/2, (hd_rect.y + hd_rect.height * 2.0) as i32, small_font_size, names_color);
+
+                    if let Ok(activity) = &activity {
+                        let outside_dates = photos_outside_dates(&images, &photos_to_save(&images, picks_only), activity);
+                        if !outside_dates.is_empty() {
+                            let dates_text = format!("{} foto sono state scattate fuori dalle date dell'attività", outside_dates.len());
+                            let dates_text_width = ui_font.measure(&dates_text, small_font_size);
+                            ui_font.draw(&mut d, &dates_text, (w - dates_text_width)/2, (hd_rect.y + hd_rect.height * 2.0) as i32 + small_font_size * 3 / 2, small_font_size, Color::ORANGE);
+                        }
+                    }
+
+                    if let Some(e) = &import_error {
+                        let import_text_width = ui_font.measure(e, small_font_size);
+                        ui_font.draw(&mut d, e, (w - import_text_width)/2, (hd_rect.y + hd_rect.height * 2.0) as i32 + small_font_size * 3, small_font_size, Color::RED);
+                    }
                     
                     if file_queue.len() > 0 {
                         let small_font_size = font_size;
@@ -636,6 +1629,13 @@ fn gui_app() {
                     let version_font_size = font_size * 9 / 10;
                     let version_text_size = d.measure_text(&version_text, version_font_size);
                     d.draw_text(&version_text, w-version_text_size - 10, h-version_font_size - 5, version_font_size, Color::WHITE.alpha(0.5));
+
+                    gui_calendar(&mut d, &date_buf, &ui_font, font_size, date_rect);
+                    gui_calendar(&mut d, &end_date_buf, &ui_font, font_size, end_date_rect);
+                    gui_dropdown_list(&mut d, &branca_dropdown, &branche_names, &ui_font, font_size, branca_rect);
+                    if let Some((_, picker)) = &event_picker {
+                        gui_picker(&mut d, picker, "Scegliere l'attività dal calendario", &ui_font, font_size);
+                    }
                 },
                 AppTab::SelectionLab => {
                     if images.is_empty() {
@@ -659,13 +1659,72 @@ fn gui_app() {
                             _ => unreachable!()
                         });
                         let load_text_width = d.measure_text(load_text.as_str(), font_size*2);
-                        let img_w = images.last().unwrap().image.width() as f32;
-                        let img_h = images.last().unwrap().image.height() as f32;
+                        let last_image = images.len() - 1;
+                        images[last_image].load_texture(&mut d, &thread, frame);
+                        let img_w = images[last_image].dimensions().0 as f32;
+                        let img_h = images[last_image].dimensions().1 as f32;
                         let scale_x = w as f32 /img_w;
                         let scale_y = h as f32 /img_h;
                         let scale = scale_x.max(scale_y);
-                        d.draw_texture_ex(&images.last().unwrap().texture, rvec2(w as f32 / 2.0 - scale * img_w * 0.5, h as f32 / 2.0 - scale * img_h * 0.5), 0.0, scale, Color::WHITE.alpha(0.5));
+                        if let Some(texture) = &images[last_image].texture {
+                            d.draw_texture_ex(texture, rvec2(w as f32 / 2.0 - scale * img_w * 0.5, h as f32 / 2.0 - scale * img_h * 0.5), 0.0, scale, Color::WHITE.alpha(0.5));
+                        }
                         d.draw_text(&load_text, (w-load_text_width)/2, h*3/7, font_size*2, Color::WHITE);
+                    } else if let Some(v) = &viewer {
+                        d.clear_background(Color::BLACK);
+                        let bounds = rrect(0.0, 0.0, w as f32, h as f32);
+                        if v.texture.is_none() {
+                            images[v.image].load_texture(&mut d, &thread, frame);
+                        }
+                        if let Some(texture) = v.texture.as_ref().or(images[v.image].texture.as_ref()) {
+                            v.zoom_pan.draw(&mut d, texture, bounds);
+                        }
+
+                        let view = filtered_view(&images, list_filter, min_rating);
+                        let position = view.iter().position(|i| *i == v.image).unwrap_or(0);
+                        let zoom_percent = v.zoom_pan.scale(shown_size(&v.texture, &images[v.image]), bounds) * 100.0;
+                        let info_text = format!("{}/{}  {}  {:.0}%{}", position + 1, view.len(), images[v.image].filename, zoom_percent,
+                            if v.texture.is_none() { " (anteprima)" } else { "" });
+                        draw_outlined_text(&mut d, &info_text, 10, 10, font_size, font_size/10, Color::WHITE, Color::BLACK);
+
+                        let help_text = "Z: 100% / adatta   Rotella: zoom   Frecce: foto   ESC: chiudi";
+                        let help_font_size = font_size * 3 / 4;
+                        draw_outlined_text(&mut d, help_text, 10, h - help_font_size - 10, help_font_size, help_font_size/10, Color::WHITE.alpha(0.7), Color::BLACK);
+                    } else if let Some(c) = &compare {
+                        d.clear_background(Color::BLACK);
+                        let panels = c.panels(rrect(0.0, 0.0, w as f32, (h - font_size * 2) as f32));
+                        for (n, bounds) in panels.iter().enumerate() {
+                            if c.textures[n].is_none() {
+                                images[c.images[n]].load_texture(&mut d, &thread, frame);
+                            }
+                            let img = &images[c.images[n]];
+                            if let Some(texture) = c.textures[n].as_ref().or(img.texture.as_ref()) {
+                                c.zoom_pan.draw(&mut d, texture, *bounds);
+                            }
+
+                            let (flag_text, flag_color) = match img.meta.flag {
+                                PickFlag::Unflagged => ("", Color::WHITE),
+                                PickFlag::Pick => ("SCELTA", Color::LIME),
+                                PickFlag::Reject => ("SCARTATA", Color::RED),
+                            };
+                            if img.meta.flag == PickFlag::Reject {
+                                d.draw_rectangle_rec(*bounds, Color::BLACK.alpha(0.5));
+                            }
+                            if n == c.active {
+                                d.draw_rectangle_lines_ex(*bounds, 4.0, THEME_COLOR);
+                            } else {
+                                d.draw_rectangle_lines_ex(*bounds, 1.0, Color::DARKGRAY);
+                            }
+
+                            let label_text = format!("{}  {}", n + 1, img.filename);
+                            draw_outlined_text(&mut d, &label_text, bounds.x as i32 + 10, bounds.y as i32 + 10, font_size, font_size/10, Color::WHITE, Color::BLACK);
+                            let meta_text = format!("{} {}", "*".repeat(img.meta.rating as usize), flag_text);
+                            draw_outlined_text(&mut d, &meta_text, bounds.x as i32 + 10, bounds.y as i32 + 10 + font_size * 3 / 2, font_size, font_size/10, flag_color, Color::BLACK);
+                        }
+
+                        let help_text = "1...4 o frecce: scegli riquadro   X: scarta   P o INVIO: tieni questa e scarta le altre   Z: 100% / adatta   ESC: chiudi";
+                        let help_font_size = font_size * 3 / 4;
+                        d.draw_text(help_text, 10, h - font_size - help_font_size / 2, help_font_size, Color::WHITE.alpha(0.7));
                     } else {
                         let upload_text = "upload";
                         let upload_button_width = d.measure_text(&upload_text, font_size) as f32 + 20.0 * 2.0;
@@ -676,15 +1735,66 @@ fn gui_app() {
                             upload_button_width, upload_button_height
                         );
 
-                        let img_w = images[file_list_active as usize].image.width() as f32;
-                        let img_h = images[file_list_active as usize].image.height() as f32;
-                        let scale_x = (w as f32 * 4.0/5.0)/img_w;
-                        let scale_y = (h as f32 * 4.0/5.0)/img_h;
-                        let scale = scale_x.min(scale_y);
+                        let view = filtered_view(&images, list_filter, min_rating);
+
+                        if let (false, Some(&active_image)) = (grid_view, view.get(file_list_active as usize)) {
+                            images[active_image].load_texture(&mut d, &thread, frame);
+                            let img_w = images[active_image].dimensions().0 as f32;
+                            let img_h = images[active_image].dimensions().1 as f32;
+                            let scale_x = (w as f32 * 4.0/5.0)/img_w;
+                            let scale_y = (h as f32 * 4.0/5.0)/img_h;
+                            let scale = scale_x.min(scale_y);
 
-                        let img_x = w as f32 * (2.0 + 3.0) / 8.0 - (img_w * scale) / 2.0 - (w as f32 - upload_button_rect.x) / 2.0;
-                        let img_y = (h as f32 / 5.0).max(167.0);
-                        d.draw_texture_ex(&images[file_list_active as usize].texture, rvec2(img_x, img_y), 0.0, scale, Color::WHITE);
+                            let img_x = w as f32 * (2.0 + 3.0) / 8.0 - (img_w * scale) / 2.0 - (w as f32 - upload_button_rect.x) / 2.0;
+                            let img_y = (h as f32 / 5.0).max(167.0);
+                            if let Some(texture) = &images[active_image].texture {
+                                d.draw_texture_ex(texture, rvec2(img_x, img_y), 0.0, scale, Color::WHITE);
+                            }
+
+                            let flags = images[active_image].quality.flags();
+                            if !flags.is_empty() {
+                                let flags_text = flags.iter().map(|(_, desc)| desc.as_str()).collect::<Vec<_>>().join(", ");
+                                draw_outlined_text(&mut d, &flags_text, img_x as i32 + 10, img_y as i32 + 10, font_size, 2, Color::ORANGE, Color::BLACK);
+                            }
+
+                            let meta = &images[active_image].meta;
+                            let (flag_text, flag_color) = match meta.flag {
+                                PickFlag::Unflagged => ("", Color::WHITE),
+                                PickFlag::Pick => ("SCELTA", Color::LIME),
+                                PickFlag::Reject => ("SCARTATA", Color::RED),
+                            };
+                            let meta_text = format!("{} {}", "*".repeat(meta.rating as usize), flag_text);
+                            let meta_text_width = d.measure_text(&meta_text, font_size * 2);
+                            draw_outlined_text(&mut d, &meta_text, (img_x + img_w * scale) as i32 - meta_text_width - 10, img_y as i32 + 10, font_size * 2, 2, flag_color, Color::BLACK);
+                            if meta.private {
+                                let private_text = "PRIVATA (non verrà caricata)";
+                                let private_text_width = d.measure_text(private_text, font_size);
+                                draw_outlined_text(&mut d, private_text, (img_x + img_w * scale) as i32 - private_text_width - 10, img_y as i32 + 20 + font_size * 2, font_size, 2, Color::VIOLET, Color::BLACK);
+                            }
+
+                            preview_rect = rrect(img_x, img_y, img_w * scale, img_h * scale);
+                            if let Some(tool) = &redact_tool {
+                                for area in meta.redactions.iter() {
+                                    draw_redaction_outline(&mut d, &area.rotated(meta.rotation), preview_rect, Color::YELLOW);
+                                }
+                                if let Some(start) = tool.drag_start {
+                                    let mouse = d.get_mouse_position();
+                                    let pointed = rvec2((mouse.x - preview_rect.x) / preview_rect.width, (mouse.y - preview_rect.y) / preview_rect.height);
+                                    draw_redaction_outline(&mut d, &tool.area(start, pointed), preview_rect, THEME_COLOR);
+                                }
+
+                                let help_font_size = font_size * 3 / 4;
+                                let mode_text = format!("Oscuramento: {}, {}", tool.shape.name(), tool.effect.name());
+                                let help_text = "Trascinare: nuova zona   E: rettangolo/ellisse   B: pixel/sfocatura   Click destro: togli zona   CANC: togli l'ultima   ESC: esci";
+                                let help_y = (img_y + img_h * scale) as i32 - help_font_size * 3 - 10;
+                                draw_outlined_text(&mut d, &mode_text, img_x as i32 + 10, help_y, font_size, 2, Color::YELLOW, Color::BLACK);
+                                draw_outlined_text(&mut d, help_text, img_x as i32 + 10, help_y + font_size * 3 / 2, help_font_size, 2, Color::WHITE, Color::BLACK);
+                            }
+                        } else if view.is_empty() {
+                            let empty_text = format!("Nessuna foto {}", list_filter);
+                            let empty_text_width = d.measure_text(&empty_text, font_size*2);
+                            d.draw_text(&empty_text, (w-empty_text_width)/2, h*3/7, font_size*2, Color::GRAY);
+                        }
 
                         if let Some(tab) = draw_tab_buttons(&mut d, app_tab, w as f32, h as f32, font_size) {
                             next_tab = tab;
@@ -692,8 +1802,10 @@ fn gui_app() {
 
 
                         let upload_text_cstr = CString::new(upload_text).unwrap_or_default();
-                        let inputs_vec = vec![&titolo_buf, &branca_buf, &giorno_buf, &mese_buf, &anno_buf, &server_buf, &utente_buf, &pw_buf];
-                        let input_not_given = inputs_vec.iter().any(|x| x.is_empty());
+                        // in the order of the widgets in the `Dati` tab
+                        // the end date can be left empty
+                        let inputs_vec = vec![titolo_buf.is_empty(), branca_dropdown.selected().is_none(), date_buf.is_empty(), false, server_buf.is_empty(), utente_buf.is_empty(), pw_buf.is_empty()];
+                        let input_not_given = inputs_vec.iter().any(|empty| *empty);
                         let upload_pressed = d.gui_button(upload_button_rect, Some(upload_text_cstr.as_c_str()));
 
                         if upload_pressed {
@@ -701,91 +1813,244 @@ fn gui_app() {
 
                             if input_not_given {
                                 text_box_active = -1;
-                                for b in inputs_vec.iter() {
+                                for empty in inputs_vec.iter() {
                                     text_box_active += 1;
-                                    if b.is_empty() {
+                                    if *empty {
                                         break;
                                     }
                                 }
                                 upload_status = UploadStatus::Error(format!("Alcune voci nella scheda `{}` non sono state compilate.", AppTab::InputData));
+                            } else if let Err(e) = date_range(&date_buf, &end_date_buf) {
+                                text_box_active = if date_buf.date().is_err() { 2 } else { 3 };
+                                upload_status = UploadStatus::Error(e);
                             }
                         }
                         
                         {
-                            let load_text = format!("{}/{}", file_list_active+1, images.len());
+                            let load_text = if list_filter == ListFilter::All && min_rating == 0 {
+                                format!("{}/{}", file_list_active+1, images.len())
+                            } else if min_rating == 0 {
+                                format!("{}/{} ({} su {})", (file_list_active+1).min(view.len() as i32), view.len(), list_filter, images.len())
+                            } else {
+                                format!("{}/{} ({}, almeno {} stelle, su {})", (file_list_active+1).min(view.len() as i32), view.len(), list_filter, min_rating, images.len())
+                            };
+                            let selected_count = images.iter().filter(|img| img.selected).count();
+                            let load_text = if selected_count > 0 {
+                                format!("{} - {} selezionate", load_text, selected_count)
+                            } else {
+                                load_text
+                            };
                             let load_text_width = d.measure_text(load_text.as_str(), font_size);
                             draw_outlined_text(&mut d, load_text.as_str(), w*5/8 - load_text_width/2, h-font_size, font_size, 2, Color::WHITE, Color::BLACK);
                         }
 
-                        let item_height = d.gui_get_style(GuiControl::LISTVIEW, GuiListViewProperty::LIST_ITEMS_HEIGHT as i32) + d.gui_get_style(GuiControl::LISTVIEW, GuiListViewProperty::LIST_ITEMS_SPACING as i32);
-                        let max_viewable_index_offset = (h * 4 / 5) / item_height;
-                        if list_moved_by_key {
-                            while file_list_scroll_index + max_viewable_index_offset - 1 <= file_list_active && file_list_scroll_index < images.len() as i32 {
-                                file_list_scroll_index += 1;
-                            }
-                            while file_list_scroll_index + 1 > file_list_active && file_list_scroll_index > 0 {
-                                file_list_scroll_index -= 1;
+                        let shift_down = d.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || d.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                        let ctrl_down = check_ctrl_shortcut(&d, None);
+
+                        if grid_view {
+                            let grid_y = (h as f32 / 5.0).max(167.0);
+                            let grid_rect = rrect(font_size as f32, grid_y, w as f32 - font_size as f32 * 2.0, upload_button_rect.y - grid_y - font_size as f32);
+                            let cell_size = grid_thumb_size;
+                            grid_columns = ((grid_rect.width / cell_size).floor() as i32).max(1);
+                            let visible_rows = ((grid_rect.height / cell_size).floor() as i32).max(1);
+                            let total_rows = (view.len() as i32 + grid_columns - 1) / grid_columns;
+
+                            let mouse = d.get_mouse_position();
+                            let mouse_in_grid = unsafe { CheckCollisionPointRec(mouse.into(), grid_rect.into()) };
+                            let wheel = d.get_mouse_wheel_move();
+                            if mouse_in_grid && wheel != 0.0 {
+                                if ctrl_down {
+                                    grid_thumb_size = (grid_thumb_size + wheel.signum() * GRID_THUMB_SIZE_STEP).clamp(MIN_GRID_THUMB_SIZE, MAX_GRID_THUMB_SIZE);
+                                } else {
+                                    grid_scroll_row -= wheel.signum() as i32;
+                                }
                             }
-                            list_moved_by_key = false;
-                        }
 
-                        let list_rect = rrect(0.0, (h as f32 / 5.0).max(167.0), w as f32/6.0, (h as f32 * 4.0 / 5.0).min(h as f32-167.0));
+                            if list_moved_by_key {
+                                let active_row = file_list_active / grid_columns;
+                                if active_row < grid_scroll_row {
+                                    grid_scroll_row = active_row;
+                                }
+                                if active_row >= grid_scroll_row + visible_rows {
+                                    grid_scroll_row = active_row - visible_rows + 1;
+                                }
+                                list_moved_by_key = false;
+                            }
+                            grid_scroll_row = grid_scroll_row.min(total_rows - visible_rows).max(0);
+
+                            if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && mouse_in_grid {
+                                let column = ((mouse.x - grid_rect.x) / cell_size).floor() as i32;
+                                let row = ((mouse.y - grid_rect.y) / cell_size).floor() as i32;
+                                let clicked = (grid_scroll_row + row) * grid_columns + column;
+                                if column < grid_columns && (clicked as usize) < view.len() {
+                                    click_image(&mut images, &view, clicked, file_list_active, &mut selection_anchor, ctrl_down, shift_down);
+                                    file_list_active = clicked;
+                                    list_drag = Some(ListDrag { from: clicked, start: mouse, dragging: false });
+                                }
+                            }
 
-                        if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-                            let mouse_in_boundaries = unsafe { CheckCollisionPointRec(d.get_mouse_position().into(), list_rect.into())};
-                            if mouse_in_boundaries {
-                                let idx = ((d.get_mouse_y() as f32 - list_rect.y) / item_height as f32).floor();
-                                if idx >= 0.0 {
-                                    file_list_active = file_list_scroll_index + idx as i32;
+                            let first_cell = grid_scroll_row * grid_columns;
+                            for (i, img_index) in view.iter().enumerate().skip(first_cell as usize).take((visible_rows * grid_columns) as usize) {
+                                let cell = i as i32 - first_cell;
+                                let x = grid_rect.x + (cell % grid_columns) as f32 * cell_size;
+                                let y = grid_rect.y + (cell / grid_columns) as f32 * cell_size;
+                                if images[*img_index].selected {
+                                    d.draw_rectangle(x as i32, y as i32, cell_size as i32, cell_size as i32, THEME_COLOR.alpha(0.35));
+                                }
+                                if i as i32 == file_list_active {
+                                    d.draw_rectangle_lines_ex(rrect(x, y, cell_size, cell_size), 2.0, Color::WHITE);
                                 }
+                                let padding = cell_size * 0.05;
+                                images[*img_index].load_texture(&mut d, &thread, frame);
+                                draw_thumbnail(&mut d, &images[*img_index], *img_index + 1, rrect(x + padding, y + padding, cell_size - padding * 2.0, cell_size - padding * 2.0), i as i32 == file_list_active, font_size);
                             }
-                        }
 
-                        let list_text = images.iter()
-                            .skip(file_list_scroll_index as usize).take(max_viewable_index_offset as usize)
-                            .map(|a| a.filename.clone()).collect::<Vec<_>>().join("\n");
-                        let list_cstr_text = CString::new(list_text).unwrap_or_default();
-                        // let list_cstr = CString::new(list_text).unwrap_or_default();
-                        
-                        let mut scroll_idx = 0;
-                        let preview_width = w / 4;
-                        d.draw_rectangle(list_rect.x as i32, list_rect.y as i32, preview_width, list_rect.height as i32, Color::GRAY);
-                        let mut idx = file_list_active - file_list_scroll_index;
-                        d.gui_list_view(list_rect, Some(list_cstr_text.as_c_str()), &mut scroll_idx, &mut idx);
-                        file_list_active = file_list_scroll_index + idx;
-                        // println!("active: {} | scroll: {}",file_list_active,file_list_scroll_index);
+                            if let Some(mut drag) = list_drag.take() {
+                                if (mouse.x - drag.start.x).abs() + (mouse.y - drag.start.y).abs() > cell_size / 8.0 {
+                                    drag.dragging = true;
+                                }
 
-                        
-                        for (i, img) in images.iter().skip(file_list_scroll_index as usize).take(max_viewable_index_offset as usize).enumerate() {
-                            let max_w = preview_width as f32 - list_rect.width;
-                            let max_h = item_height as f32;
-                            let img_w = img.image.width() as f32;
-                            let img_h = img.image.height() as f32;
-                            let scale_x = max_w /img_w;
-                            let scale_y = max_h /img_h;
-                            let mut scale = scale_x.min(scale_y);
-
-                            let mut y = (h as f32 / 5.0).max(167.0) + i as f32 * item_height as f32;
-                            let mut x = list_rect.width;
-
-                            let mut color_fade = 1.0;
-                            
-                            if i as i32 != file_list_active - file_list_scroll_index {
-                                scale *= 0.85;
-                                color_fade *= 0.9;
+                                if drag.dragging && d.get_time() - last_autoscroll_time > AUTOSCROLL_INTERVAL {
+                                    if mouse.y < grid_rect.y + cell_size / 4.0 && grid_scroll_row > 0 {
+                                        grid_scroll_row -= 1;
+                                        last_autoscroll_time = d.get_time();
+                                    } else if mouse.y > grid_rect.y + grid_rect.height - cell_size / 4.0 && grid_scroll_row + visible_rows < total_rows {
+                                        grid_scroll_row += 1;
+                                        last_autoscroll_time = d.get_time();
+                                    }
+                                }
+
+                                // dropping before the photo under the mouse, or after it when on its right half
+                                let column = ((mouse.x - grid_rect.x) / cell_size).round() as i32;
+                                let column = column.min(grid_columns).max(0);
+                                let row = ((mouse.y - grid_rect.y) / cell_size).floor() as i32;
+                                let row = row.min(visible_rows - 1).max(0);
+                                let drop_position = ((grid_scroll_row + row) * grid_columns + column).min(view.len() as i32).max(0);
+
+                                if d.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+                                    if let (true, Some(&dragged_image)) = (drag.dragging, view.get(drag.from as usize)) {
+                                        list_drop = Some((dragged_image, drop_position));
+                                    } else if !ctrl_down && !shift_down {
+                                        // plain click on a selected photo
+                                        clear_selection(&mut images);
+                                    }
+                                } else {
+                                    if drag.dragging {
+                                        let line_x = grid_rect.x + column as f32 * cell_size;
+                                        let line_y = grid_rect.y + row as f32 * cell_size;
+                                        d.draw_line_ex(rvec2(line_x, line_y), rvec2(line_x, line_y + cell_size), 4.0, THEME_COLOR);
+                                    }
+                                    list_drag = Some(drag);
+                                }
+                            }
+                        } else {
+                            let item_height = d.gui_get_style(GuiControl::LISTVIEW, GuiListViewProperty::LIST_ITEMS_HEIGHT as i32) + d.gui_get_style(GuiControl::LISTVIEW, GuiListViewProperty::LIST_ITEMS_SPACING as i32);
+                            let max_viewable_index_offset = (h * 4 / 5) / item_height;
+                            if list_moved_by_key {
+                                while file_list_scroll_index + max_viewable_index_offset - 1 <= file_list_active && file_list_scroll_index < view.len() as i32 {
+                                    file_list_scroll_index += 1;
+                                }
+                                while file_list_scroll_index + 1 > file_list_active && file_list_scroll_index > 0 {
+                                    file_list_scroll_index -= 1;
+                                }
+                                list_moved_by_key = false;
                             }
 
-                            y += (max_h - img_h * scale) / 2.0;
-                            x += (max_w - img_w * scale) / 2.0;
+                            let list_rect = rrect(0.0, (h as f32 / 5.0).max(167.0), w as f32/6.0, (h as f32 * 4.0 / 5.0).min(h as f32-167.0));
+                            let preview_width = w / 4;
+                            // file names and thumbnails
+                            let strip_rect = rrect(list_rect.x, list_rect.y, preview_width as f32, list_rect.height);
+
+                            if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                                let mouse_in_boundaries = unsafe { CheckCollisionPointRec(d.get_mouse_position().into(), strip_rect.into())};
+                                if mouse_in_boundaries {
+                                    let idx = ((d.get_mouse_y() as f32 - list_rect.y) / item_height as f32).floor();
+                                    if idx >= 0.0 {
+                                        let clicked = file_list_scroll_index + idx as i32;
+                                        if (clicked as usize) < view.len() {
+                                            click_image(&mut images, &view, clicked, file_list_active, &mut selection_anchor, ctrl_down, shift_down);
+                                            list_drag = Some(ListDrag { from: clicked, start: d.get_mouse_position(), dragging: false });
+                                        }
+                                        file_list_active = clicked;
+                                    }
+                                }
+                            }
+
+                            let list_text = view.iter()
+                                .skip(file_list_scroll_index as usize).take(max_viewable_index_offset as usize)
+                                .map(|a| images[*a].filename.clone()).collect::<Vec<_>>().join("\n");
+                            let list_cstr_text = CString::new(list_text).unwrap_or_default();
+                            // let list_cstr = CString::new(list_text).unwrap_or_default();
                             
-                            d.draw_texture_ex(&img.texture, rvec2(x, y), 0.0, scale, Color::WHITE);
+                            let mut scroll_idx = 0;
+                            d.draw_rectangle(list_rect.x as i32, list_rect.y as i32, preview_width, list_rect.height as i32, Color::GRAY);
+                            let mut idx = file_list_active - file_list_scroll_index;
+                            d.gui_list_view(list_rect, Some(list_cstr_text.as_c_str()), &mut scroll_idx, &mut idx);
+                            file_list_active = file_list_scroll_index + idx;
+                            // println!("active: {} | scroll: {}",file_list_active,file_list_scroll_index);
+
+                            for (i, img_index) in view.iter().skip(file_list_scroll_index as usize).take(max_viewable_index_offset as usize).enumerate() {
+                                let y = list_rect.y + i as f32 * item_height as f32;
+                                if images[*img_index].selected {
+                                    d.draw_rectangle(list_rect.x as i32, y as i32, preview_width, item_height, THEME_COLOR.alpha(0.35));
+                                }
+
+                                let thumbnail_rect = rrect(list_rect.width, y, preview_width as f32 - list_rect.width, item_height as f32);
+                                images[*img_index].load_texture(&mut d, &thread, frame);
+                                draw_thumbnail(&mut d, &images[*img_index], *img_index + 1, thumbnail_rect, i as i32 == file_list_active - file_list_scroll_index, font_size);
+                            }
                             
-                            let num_text = format!("{}", i+file_list_scroll_index as usize + 1);
-                            let outline_size = 2;
-                            draw_outlined_text(&mut d, &num_text, x as i32 + outline_size * 2, y as i32 + outline_size + 1, font_size, outline_size, Color::WHITE.alpha(color_fade), Color::BLACK.alpha(color_fade/2.0));
+                            file_list_scroll_index += scroll_idx;
+
+                            if let Some(mut drag) = list_drag.take() {
+                                let mouse_y = d.get_mouse_y() as f32;
+                                if (mouse_y - drag.start.y).abs() > item_height as f32 / 4.0 {
+                                    drag.dragging = true;
+                                }
+
+                                if drag.dragging && d.get_time() - last_autoscroll_time > AUTOSCROLL_INTERVAL {
+                                    if mouse_y < list_rect.y + item_height as f32 / 2.0 && file_list_scroll_index > 0 {
+                                        file_list_scroll_index -= 1;
+                                        last_autoscroll_time = d.get_time();
+                                    } else if mouse_y > list_rect.y + list_rect.height - item_height as f32 / 2.0 && file_list_scroll_index + max_viewable_index_offset < view.len() as i32 {
+                                        file_list_scroll_index += 1;
+                                        last_autoscroll_time = d.get_time();
+                                    }
+                                }
+
+                                // position in the view before which the photos are dropped
+                                let drop_position = (((mouse_y - list_rect.y) / item_height as f32).round() as i32 + file_list_scroll_index).min(view.len() as i32).max(0);
+
+                                if d.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
+                                    if let (true, Some(&dragged_image)) = (drag.dragging, view.get(drag.from as usize)) {
+                                        list_drop = Some((dragged_image, drop_position));
+                                    } else if !ctrl_down && !shift_down {
+                                        // plain click on a selected photo
+                                        clear_selection(&mut images);
+                                    }
+                                } else {
+                                    if drag.dragging {
+                                        let line_y = list_rect.y + (drop_position - file_list_scroll_index) as f32 * item_height as f32;
+                                        d.draw_line_ex(rvec2(strip_rect.x, line_y), rvec2(strip_rect.x + strip_rect.width, line_y), 4.0, THEME_COLOR);
+                                    }
+                                    list_drag = Some(drag);
+                                }
+                            }
+                        }
+
+                        if let Some(position_buf) = &move_position_buf {
+                            let count = operation_targets(&images, &view, file_list_active).len();
+                            let prompt_text = format!("Spostare {} foto nella posizione: {}{}", count, String::from_utf8(position_buf.clone()).unwrap_or_default(), if (d.get_time() / 0.5) as u32 % 2 == 0 { "_" } else { " " });
+                            let hint_text = "(INVIO per confermare, ESC per annullare)";
+                            let prompt_text_width = d.measure_text(&prompt_text, font_size * 2);
+                            let hint_text_width = d.measure_text(hint_text, font_size);
+                            let box_width = prompt_text_width.max(hint_text_width) + font_size * 4;
+                            let box_height = font_size * 6;
+                            d.draw_rectangle((w - box_width) / 2, (h - box_height) / 2, box_width, box_height, BACKGROUND_COLOR.alpha(0.95));
+                            d.draw_rectangle_lines((w - box_width) / 2, (h - box_height) / 2, box_width, box_height, THEME_COLOR);
+                            d.draw_text(&prompt_text, (w - prompt_text_width) / 2, h / 2 - font_size * 2, font_size * 2, Color::WHITE);
+                            d.draw_text(hint_text, (w - hint_text_width) / 2, h / 2 + font_size, font_size, Color::GRAY);
                         }
-                        
-                        file_list_scroll_index += scroll_idx;
                         
                     }
                 }
@@ -820,13 +2085,35 @@ fn gui_app() {
                     d.draw_text(upload_label_text.as_str(), (w-upload_text_width)/2, h*3/7, font_size*2, Color::WHITE);
 
                     let progress_bar_width = w as f32 / 3.0;
-                    d.gui_progress_bar(rrect((w as f32 - progress_bar_width) / 2.0, h as f32 * 0.5, progress_bar_width, 25.0), None, None, &mut (i as f32), 0.0, (images.len()-1) as f32);
+                    d.gui_progress_bar(rrect((w as f32 - progress_bar_width) / 2.0, h as f32 * 0.5, progress_bar_width, 25.0), None, None, &mut (i as f32), 0.0, (images_to_save.len()-1) as f32);
                 },
                 UploadStatus::DoneSaving => {
+                    let remote_path = match config.season.folder(activity.date) {
+                        Some(season_dir) => format!("{}/{}/{}/", server, season_dir, image_dir),
+                        None => format!("{}/{}/", server, image_dir),
+                    };
+                    let remote_text = if files_to_upload.is_empty() {
+                        String::from("Tutte le foto sono private, nessuna foto verrà caricata")
+                    } else {
+                        format!("{} foto verranno caricate in `{}`", files_to_upload.len(), remote_path)
+                    };
+                    let remote_text_width = ui_font.measure(&remote_text, font_size);
+                    ui_font.draw(&mut d, &remote_text, (w - remote_text_width)/2, h/2 - font_size*3, font_size, Color::GRAY);
+
+                    let private_text = match &config.private_folder {
+                        _ if private_count == 0 => None,
+                        Some(private_folder) => Some(format!("{} foto private sono state salvate solo in `{}/{}/`", private_count, image_dir, private_folder)),
+                        None => Some(format!("{} foto private non sono state salvate", private_count)),
+                    };
+                    if let Some(private_text) = private_text {
+                        let private_text_width = ui_font.measure(&private_text, font_size);
+                        ui_font.draw(&mut d, &private_text, (w - private_text_width)/2, h/2 - font_size*3/2, font_size, Color::VIOLET);
+                    }
+
                     let upload_button_width = 550.0;
                     let upload_button_height = font_size as f32*2.0;
                     let upload_text = CString::new(format!("Caricare le foto sul server")).unwrap_or_default();
-                    if d.gui_button(rrect((w as f32 - upload_button_width) / 2.0, (h as f32 - upload_button_height)/2.0, upload_button_width, upload_button_height ), Some(upload_text.as_c_str())) {
+                    if !files_to_upload.is_empty() && d.gui_button(rrect((w as f32 - upload_button_width) / 2.0, (h as f32 - upload_button_height)/2.0 + font_size as f32, upload_button_width, upload_button_height ), Some(upload_text.as_c_str())) {
                         upload_status = UploadStatus::Connecting;
                     }
                 },
@@ -859,6 +2146,24 @@ fn gui_app() {
                     let progress_bar_width = w as f32 / 3.0;
                     d.gui_progress_bar(rrect((w as f32 - progress_bar_width) / 2.0, h as f32 * 0.5, progress_bar_width, 25.0), None, None, &mut (i as f32), 0.0, (files_to_upload.len()-1) as f32);
                 },
+                UploadStatus::OutsideDates(ref warning) => {
+                    let warning_text_width = d.measure_text(warning.as_str(), font_size);
+                    d.draw_text(warning.as_str(), (w-warning_text_width)/2, h*3/7, font_size, Color::ORANGE);
+
+                    let button_width = 400.0;
+                    let button_height = font_size as f32*2.0;
+                    let button_padding = font_size as f32;
+                    let buttons_y = h as f32 * 3.0/7.0 + font_size as f32*3.0 + button_height/2.0;
+                    let save_text = CString::new("Salvare comunque").unwrap_or_default();
+                    let back_text = CString::new("Indietro").unwrap_or_default();
+                    if d.gui_button(rrect(w as f32/2.0 - button_width - button_padding/2.0, buttons_y, button_width, button_height), Some(save_text.as_c_str())) {
+                        upload_status = UploadStatus::CreatingDir;
+                    } else if d.gui_button(rrect(w as f32/2.0 + button_padding/2.0, buttons_y, button_width, button_height), Some(back_text.as_c_str())) {
+                        upload = false;
+                        upload_status = UploadStatus::None;
+                        next_tab = AppTab::InputData;
+                    }
+                },
                 UploadStatus::Error(ref e) => {
                     let error_text_width = d.measure_text(e.as_str(), font_size);
                     d.draw_text(e.as_str(), (w-error_text_width)/2, h*3/7, font_size, Color::RED);
@@ -886,7 +2191,9 @@ fn gui_app() {
             
         }
         // d.draw_text(title, (w-title_width)/2, 25, font_size * 3, THEME_COLOR);
-        draw_outlined_text(&mut d, title, (w-title_width)/2, 25, font_size*3, font_size/10, THEME_COLOR, THEME_COLOR);
+        if viewer.is_none() && compare.is_none() {
+            draw_outlined_text(&mut d, title, (w-title_width)/2, 25, font_size*3, font_size/10, THEME_COLOR, THEME_COLOR);
+        }
     }
 
     if !images.is_empty() {
@@ -901,5 +2208,49 @@ fn gui_app() {
 }
 
 fn main() {
-    gui_app()
+    // `fototpm --console` processes the photos listed in `settings.json` without opening a window
+    if std::env::args().skip(1).any(|arg| arg == "--console") {
+        console::console_app()
+    } else {
+        gui_app()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the order of `len` photos after the moves
+    fn replay(edits: &[Edit], len: usize) -> Vec<usize> {
+        let mut order = (0..len).collect::<Vec<_>>();
+        for edit in edits {
+            match edit {
+                Edit::Move(from, to) => {
+                    let moved = order.remove(*from);
+                    order.insert(*to, moved);
+                },
+                _ => panic!("only moves are expected"),
+            }
+        }
+        order
+    }
+
+    #[test]
+    fn move_to_position_keeps_the_targets_together() {
+        // forward
+        assert_eq!(replay(&move_to_position(&[0, 1], 5, 8), 8), [2, 3, 4, 5, 6, 0, 1, 7]);
+        assert_eq!(replay(&move_to_position(&[0, 3], 5, 8), 8), [1, 2, 4, 5, 6, 0, 3, 7]);
+        // backward
+        assert_eq!(replay(&move_to_position(&[4, 6], 1, 8), 8), [0, 4, 6, 1, 2, 3, 5, 7]);
+        // both, around the targets
+        assert_eq!(replay(&move_to_position(&[1, 4, 7], 3, 8), 8), [0, 2, 3, 1, 4, 7, 5, 6]);
+        // already in place
+        assert!(move_to_position(&[2, 3], 2, 8).is_empty());
+
+        // dragged down before photo 5, and up before photo 1
+        assert_eq!(replay(&move_to_position(&[0, 1], position_before(&[0, 1], 5), 8), 8), [2, 3, 4, 0, 1, 5, 6, 7]);
+        assert_eq!(replay(&move_to_position(&[2, 6], position_before(&[2, 6], 1), 8), 8), [0, 2, 6, 1, 3, 4, 5, 7]);
+        // dragged to the end of the list
+        assert_eq!(replay(&move_to_position(&[1, 3], position_before(&[1, 3], 8), 8), 8), [0, 2, 4, 5, 6, 7, 1, 3]);
+    }
 }
@@ -0,0 +1,362 @@
+// Metadata written into the saved photos, which are encoded again from the pixels and so keep
+// nothing of the original files. The same data goes both in EXIF, read by cameras, file managers
+// and most viewers, and in XMP, read by photo management software and websites.
+//
+// The photos are published and show minors, so by default (privacy mode) nothing that tells where
+// they were taken or with which camera is written: no GPS position, camera model or serial number.
+// The maker notes are never written. Before the upload the saved photos are checked again with
+// `exif::has_gps`.
+
+use std::fs;
+use std::io::{self, Cursor};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use image::{DynamicImage, ImageFormat, ImageResult};
+
+use crate::exif::{
+    Exif, DATE_TIME_FORMAT, TAG_ARTIST, TAG_BODY_SERIAL_NUMBER, TAG_COPYRIGHT, TAG_DATE_TIME_ORIGINAL, TAG_EXIF_IFD,
+    TAG_GPS_IFD, TAG_IMAGE_DESCRIPTION, TAG_MAKE, TAG_MODEL, TYPE_ASCII, TYPE_LONG,
+};
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const APP0: u8 = 0xe0;
+const APP1: u8 = 0xe1;
+// the length of a segment is written in two bytes, and counts itself
+const MAX_SEGMENT_DATA: usize = 0xffff - 2;
+
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    // as typed in the `Dati` tab, not the slugs of the file names
+    pub title: String,
+    pub branca: String,
+    pub date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    // of the single photo, from its original EXIF data
+    pub artist: Option<String>,
+    pub taken: Option<NaiveDateTime>,
+    // of the group, from the settings
+    pub copyright: String,
+    pub licence: String,
+    // of the single photo, only without the privacy mode
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub gps: Vec<Entry>,
+}
+
+impl Metadata {
+    // `Uscita al lago (LUPI, 05/03/2024)`
+    fn description(&self) -> String {
+        let days = match self.end_date {
+            Some(end_date) => format!("{} - {}", self.date.format("%d/%m/%Y"), end_date.format("%d/%m/%Y")),
+            None => self.date.format("%d/%m/%Y").to_string(),
+        };
+        let details = if self.branca.is_empty() { days } else { format!("{}, {}", self.branca, days) };
+        if self.title.is_empty() { details } else { format!("{} ({})", self.title, details) }
+    }
+
+    // the copyright notice followed by the licence, as EXIF has a single field for both
+    fn rights(&self) -> String {
+        [self.copyright.as_str(), self.licence.as_str()].iter().filter(|s| !s.is_empty()).copied().collect::<Vec<_>>().join(" - ")
+    }
+
+    /// The metadata of the activity completed with the ones of the photo read from `exif`; with
+    /// `privacy` the camera and the position are left out.
+    pub fn for_photo(&self, exif: &Exif, privacy: bool) -> Metadata {
+        let metadata = Metadata { artist: exif.artist.clone(), taken: exif.taken, ..self.clone() };
+        if privacy {
+            return metadata;
+        }
+        Metadata {
+            make: exif.make.clone(),
+            model: exif.model.clone(),
+            serial: exif.serial.clone(),
+            gps: exif.gps.clone(),
+            ..metadata
+        }
+    }
+}
+
+// entry of a directory of the EXIF data, with its value little endian
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub tag: u16,
+    pub kind: u16,
+    pub count: u32,
+    pub data: Vec<u8>,
+}
+
+impl Entry {
+    fn ascii(tag: u16, text: &str) -> Entry {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        Entry { tag, kind: TYPE_ASCII, count: data.len() as u32, data }
+    }
+
+    fn long(tag: u16, value: u32) -> Entry {
+        Entry { tag, kind: TYPE_LONG, count: 1, data: value.to_le_bytes().to_vec() }
+    }
+
+    // values longer than 4 bytes are written after the directory, at an even offset
+    fn outside_len(&self) -> usize {
+        if self.data.len() > 4 { (self.data.len() + 1) & !1 } else { 0 }
+    }
+}
+
+fn ifd_len(entries: &[Entry]) -> usize {
+    2 + entries.len() * 12 + 4 + entries.iter().map(Entry::outside_len).sum::<usize>()
+}
+
+// appends the directory to `tiff`, where it starts at `tiff.len()`; the entries must be sorted
+fn write_ifd(tiff: &mut Vec<u8>, entries: &[Entry]) {
+    let mut data_offset = tiff.len() + 2 + entries.len() * 12 + 4;
+    tiff.extend((entries.len() as u16).to_le_bytes());
+    for entry in entries {
+        tiff.extend(entry.tag.to_le_bytes());
+        tiff.extend(entry.kind.to_le_bytes());
+        tiff.extend(entry.count.to_le_bytes());
+        if entry.outside_len() == 0 {
+            tiff.extend(&entry.data);
+            tiff.extend(vec![0; 4 - entry.data.len()]);
+        } else {
+            tiff.extend((data_offset as u32).to_le_bytes());
+            data_offset += entry.outside_len();
+        }
+    }
+    // no next directory
+    tiff.extend(0u32.to_le_bytes());
+    for entry in entries.iter().filter(|entry| entry.outside_len() > 0) {
+        tiff.extend(&entry.data);
+        tiff.extend(vec![0; entry.outside_len() - entry.data.len()]);
+    }
+}
+
+// TIFF structure of the EXIF data, little endian
+fn exif_data(metadata: &Metadata) -> Vec<u8> {
+    let mut ifd0 = vec![Entry::ascii(TAG_IMAGE_DESCRIPTION, &metadata.description())];
+    if let Some(make) = &metadata.make {
+        ifd0.push(Entry::ascii(TAG_MAKE, make));
+    }
+    if let Some(model) = &metadata.model {
+        ifd0.push(Entry::ascii(TAG_MODEL, model));
+    }
+    if let Some(artist) = &metadata.artist {
+        ifd0.push(Entry::ascii(TAG_ARTIST, artist));
+    }
+    let rights = metadata.rights();
+    if !rights.is_empty() {
+        ifd0.push(Entry::ascii(TAG_COPYRIGHT, &rights));
+    }
+
+    let mut exif_ifd = Vec::new();
+    if let Some(taken) = metadata.taken {
+        exif_ifd.push(Entry::ascii(TAG_DATE_TIME_ORIGINAL, &taken.format(DATE_TIME_FORMAT).to_string()));
+    }
+    if let Some(serial) = &metadata.serial {
+        exif_ifd.push(Entry::ascii(TAG_BODY_SERIAL_NUMBER, serial));
+    }
+    let mut gps_ifd = metadata.gps.clone();
+    gps_ifd.sort_by_key(|entry| entry.tag);
+
+    // the other directories go right after the first one, whose size their pointers are part of
+    if !exif_ifd.is_empty() {
+        ifd0.push(Entry::long(TAG_EXIF_IFD, 0));
+    }
+    if !gps_ifd.is_empty() {
+        ifd0.push(Entry::long(TAG_GPS_IFD, 0));
+    }
+    let exif_offset = 8 + ifd_len(&ifd0);
+    let gps_offset = exif_offset + if exif_ifd.is_empty() { 0 } else { ifd_len(&exif_ifd) };
+    for entry in ifd0.iter_mut() {
+        match entry.tag {
+            TAG_EXIF_IFD => entry.data = (exif_offset as u32).to_le_bytes().to_vec(),
+            TAG_GPS_IFD => entry.data = (gps_offset as u32).to_le_bytes().to_vec(),
+            _ => {},
+        }
+    }
+
+    let mut tiff = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0];
+    for ifd in [&ifd0, &exif_ifd, &gps_ifd] {
+        if !ifd.is_empty() {
+            write_ifd(&mut tiff, ifd);
+        }
+    }
+    tiff
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn xmp_packet(metadata: &Metadata) -> String {
+    let alt = |name: &str, text: &str| format!("   <{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>\n", name, escape_xml(text));
+    let mut properties = String::new();
+    if !metadata.title.is_empty() {
+        properties += &alt("dc:title", &metadata.title);
+    }
+    properties += &alt("dc:description", &metadata.description());
+    if !metadata.branca.is_empty() {
+        properties += &format!("   <dc:subject><rdf:Bag><rdf:li>{}</rdf:li></rdf:Bag></dc:subject>\n", escape_xml(&metadata.branca));
+    }
+    if let Some(artist) = &metadata.artist {
+        properties += &format!("   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n", escape_xml(artist));
+    }
+    if !metadata.copyright.is_empty() {
+        properties += &alt("dc:rights", &metadata.copyright);
+    }
+    if !metadata.licence.is_empty() {
+        properties += &alt("xmpRights:UsageTerms", &metadata.licence);
+    }
+    properties += &format!("   <photoshop:DateCreated>{}</photoshop:DateCreated>\n", metadata.date.format("%Y-%m-%d"));
+
+    format!(concat!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+        " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        "  <rdf:Description rdf:about=\"\"\n",
+        "    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n",
+        "    xmlns:photoshop=\"http://ns.adobe.com/photoshop/1.0/\"\n",
+        "    xmlns:xmpRights=\"http://ns.adobe.com/xap/1.0/rights/\">\n",
+        "{}",
+        "  </rdf:Description>\n",
+        " </rdf:RDF>\n",
+        "</x:xmpmeta>\n",
+        "<?xpacket end=\"w\"?>",
+    ), properties)
+}
+
+// APP1 segment with `header` followed by `data`, `None` if it does not fit in a segment
+fn app1_segment(header: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let len = 2 + header.len() + data.len();
+    if len > MAX_SEGMENT_DATA + 2 {
+        return None;
+    }
+    let mut segment = vec![0xff, APP1];
+    segment.extend((len as u16).to_be_bytes());
+    segment.extend(header);
+    segment.extend(data);
+    Some(segment)
+}
+
+// puts the segments in the JPEG file after the start of image and the JFIF header, as the EXIF
+// segment must come first; the missing ones are skipped
+fn insert_segments(jpeg: &[u8], segments: &[Option<Vec<u8>>]) -> Vec<u8> {
+    let mut pos = 2;
+    while jpeg.get(pos) == Some(&0xff) && jpeg.get(pos + 1) == Some(&APP0) {
+        let len = match jpeg.get(pos + 2..pos + 4) {
+            Some(len) => u16::from_be_bytes([len[0], len[1]]) as usize,
+            None => break,
+        };
+        pos += 2 + len;
+    }
+    let pos = pos.min(jpeg.len());
+
+    let mut out = jpeg[..pos].to_vec();
+    for segment in segments.iter().flatten() {
+        out.extend(segment);
+    }
+    out.extend(&jpeg[pos..]);
+    out
+}
+
+/// Saves `image` as a JPEG file at `path`, with `metadata` if given.
+pub fn save_jpeg(image: &DynamicImage, path: &str, metadata: Option<&Metadata>) -> ImageResult<()> {
+    let mut jpeg = Vec::new();
+    image.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)?;
+
+    if let Some(metadata) = metadata {
+        let segments = [
+            app1_segment(EXIF_HEADER, &exif_data(metadata)),
+            app1_segment(XMP_HEADER, xmp_packet(metadata).as_bytes()),
+        ];
+        if segments.iter().any(Option::is_none) {
+            eprintln!("[WARNING]: I metadati di `{}` sono troppo lunghi, alcuni non verranno salvati.", path);
+        }
+        jpeg = insert_segments(&jpeg, &segments);
+    }
+
+    fs::write(path, jpeg).map_err(|e: io::Error| e.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exif;
+
+    // latitude 41° 53' 0" N
+    fn gps() -> Vec<Entry> {
+        let rationals = [41, 1, 53, 1, 0, 1u32].iter().flat_map(|number| number.to_le_bytes()).collect();
+        vec![
+            Entry::ascii(0x0001, "N"),
+            Entry { tag: 0x0002, kind: 5, count: 3, data: rationals },
+        ]
+    }
+
+    fn original() -> Exif {
+        Exif {
+            artist: Some(String::from("Mario Rossi")),
+            taken: NaiveDateTime::parse_from_str("2024:08:01 10:30:00", DATE_TIME_FORMAT).ok(),
+            make: Some(String::from("Canon")),
+            model: Some(String::from("EOS 90D")),
+            serial: Some(String::from("012345678901")),
+            gps: gps(),
+        }
+    }
+
+    fn activity() -> Metadata {
+        Metadata {
+            title: String::from("Uscita al lago"),
+            branca: String::from("LUPI"),
+            date: NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            copyright: String::from("Gruppo Scout Roma 1"),
+            ..Metadata::default()
+        }
+    }
+
+    // the bytes `save_jpeg` would write
+    fn jpeg(metadata: &Metadata) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(8, 8).write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg).unwrap();
+        insert_segments(&jpeg, &[
+            app1_segment(EXIF_HEADER, &exif_data(metadata)),
+            app1_segment(XMP_HEADER, xmp_packet(metadata).as_bytes()),
+        ])
+    }
+
+    #[test]
+    fn gps_written_without_privacy() {
+        let bytes = jpeg(&activity().for_photo(&original(), false));
+        assert!(exif::has_gps(&bytes));
+
+        let written = Exif::read(&bytes);
+        assert_eq!(written.make.as_deref(), Some("Canon"));
+        assert_eq!(written.model.as_deref(), Some("EOS 90D"));
+        assert_eq!(written.serial.as_deref(), Some("012345678901"));
+        assert_eq!(written.gps.len(), 2);
+        for (written, original) in written.gps.iter().zip(gps()) {
+            assert_eq!((written.tag, written.kind, written.count, &written.data), (original.tag, original.kind, original.count, &original.data));
+        }
+    }
+
+    #[test]
+    fn no_gps_with_privacy() {
+        let bytes = jpeg(&activity().for_photo(&original(), true));
+        assert!(!exif::has_gps(&bytes));
+
+        let written = Exif::read(&bytes);
+        assert!(written.make.is_none() && written.model.is_none() && written.serial.is_none());
+        assert!(written.gps.is_empty());
+        // what is not about the camera or the position is kept
+        assert_eq!(written.artist.as_deref(), Some("Mario Rossi"));
+        assert_eq!(written.taken, original().taken);
+    }
+
+    #[test]
+    fn no_gps_without_metadata() {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(8, 8).write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg).unwrap();
+        assert!(!exif::has_gps(&bytes));
+        assert!(Exif::read(&bytes).artist.is_none());
+    }
+}
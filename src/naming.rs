@@ -0,0 +1,310 @@
+// Names of the saved folder (the same on the server) and of the photos, built from templates with
+// placeholders between braces:
+//
+//     {aaaa} {aa}    year of the activity, with four or two digits
+//     {mm} {gg}      month and day of the activity
+//     {BRANCA}       branca in capital letters, {branca} as it was typed
+//     {titolo}       title of the activity
+//     {n} {n:3}      number of the photo, optionally padded with zeros to the given digits
+//     {originale}    name of the original file, without extension
+//     {fotografo}    author of the photo, from its EXIF data
+//
+// Date placeholders next to each other (or separated only by punctuation) make a single date: for
+// activities of more days it is written twice, as `start-end` (`{aa}{mm}{gg}` gives
+// `240801-240810`).
+//
+// The values are already slugs, so only the literal text of the templates has to be checked.
+
+use chrono::{Datelike, NaiveDate};
+
+pub const DEFAULT_FOLDER_TEMPLATE: &str = "{aa}{mm}{gg}_{BRANCA}_{titolo}";
+pub const DEFAULT_FILE_TEMPLATE: &str = "{aa}{mm}{gg}_{BRANCA}_{titolo}_{n:3}";
+
+const FILE_EXTENSION: &str = ".JPG";
+pub const FORBIDDEN_CHARS: &str = "/\\:*?\"<>|";
+const MAX_COUNTER_DIGITS: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    FullYear,
+    Year,
+    Month,
+    Day,
+    BrancaUpper,
+    Branca,
+    Title,
+    // digits of the zero padding
+    Number(usize),
+    Original,
+    Photographer,
+}
+
+impl Part {
+    fn from_placeholder(name: &str) -> Option<Part> {
+        Some(match name {
+            "aaaa" => Part::FullYear,
+            "aa" => Part::Year,
+            "mm" => Part::Month,
+            "gg" => Part::Day,
+            "BRANCA" => Part::BrancaUpper,
+            "branca" => Part::Branca,
+            "titolo" => Part::Title,
+            "n" => Part::Number(0),
+            "originale" => Part::Original,
+            "fotografo" => Part::Photographer,
+            _ => {
+                let digits = name.strip_prefix("n:")?.parse::<usize>().ok()?;
+                if !(1..=MAX_COUNTER_DIGITS).contains(&digits) {
+                    return None;
+                }
+                Part::Number(digits)
+            },
+        })
+    }
+
+    fn is_date(&self) -> bool {
+        matches!(self, Part::FullYear | Part::Year | Part::Month | Part::Day)
+    }
+
+    // the value changes from photo to photo
+    fn is_per_photo(&self) -> bool {
+        matches!(self, Part::Number(_) | Part::Original | Part::Photographer)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    pub date: NaiveDate,
+    // last day of activities of more days, always after `date`
+    pub end_date: Option<NaiveDate>,
+    pub branca: String,
+    pub title: String,
+}
+
+impl Activity {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.date <= date && date <= self.end_date.unwrap_or(self.date)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Photo {
+    pub number: usize,
+    pub original: String,
+    pub photographer: String,
+}
+
+#[derive(Debug, Clone)]
+struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    fn parse(template: &str) -> Result<Template, String> {
+        if template.trim().is_empty() {
+            return Err(String::from("è vuoto."));
+        }
+
+        let text = |text: &str| match text.chars().find(|c| FORBIDDEN_CHARS.contains(*c) || c.is_control()) {
+            Some(c) => Err(format!("contiene `{}`, che non è ammesso nei nomi di file.", c)),
+            None => Ok(Part::Text(text.to_owned())),
+        };
+
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            match rest.find(['{', '}']) {
+                Some(i) if rest[i..].starts_with('}') => {
+                    return Err(String::from("c'è una `}` senza la `{` corrispondente."));
+                },
+                Some(i) => {
+                    if i > 0 {
+                        parts.push(text(&rest[..i])?);
+                    }
+                    let end = rest[i..].find('}').ok_or_else(|| String::from("manca una `}`."))? + i;
+                    let name = &rest[i + 1..end];
+                    let part = Part::from_placeholder(name).ok_or_else(|| format!("il segnaposto `{{{}}}` non esiste.", name))?;
+                    parts.push(part);
+                    rest = &rest[end + 1..];
+                },
+                None => {
+                    parts.push(text(rest)?);
+                    rest = "";
+                },
+            }
+        }
+
+        Ok(Template { parts })
+    }
+
+    fn format(&self, activity: &Activity, photo: Option<&Photo>) -> String {
+        let mut name = String::new();
+        let mut i = 0;
+        while i < self.parts.len() {
+            if !self.parts[i].is_date() {
+                name += &Template::format_part(&self.parts[i], activity, photo);
+                i += 1;
+                continue;
+            }
+
+            let end = self.date_end(i);
+            name += &Template::format_date(&self.parts[i..end], activity.date);
+            if let Some(end_date) = activity.end_date {
+                name.push('-');
+                name += &Template::format_date(&self.parts[i..end], end_date);
+            }
+            i = end;
+        }
+        name
+    }
+
+    // end of the date that starts with the part at `start`
+    fn date_end(&self, start: usize) -> usize {
+        let is_separator = |part: &Part| matches!(part, Part::Text(text) if !text.chars().any(char::is_alphanumeric));
+        let mut end = start + 1;
+        loop {
+            match (self.parts.get(end), self.parts.get(end + 1)) {
+                (Some(part), _) if part.is_date() => end += 1,
+                (Some(separator), Some(part)) if is_separator(separator) && part.is_date() => end += 2,
+                _ => return end,
+            }
+        }
+    }
+
+    fn format_date(parts: &[Part], date: NaiveDate) -> String {
+        parts.iter().map(|part| match part {
+            Part::Text(text) => text.clone(),
+            Part::FullYear => format!("{:04}", date.year()),
+            Part::Year => format!("{:02}", date.year() % 100),
+            Part::Month => format!("{:02}", date.month()),
+            Part::Day => format!("{:02}", date.day()),
+            _ => String::new(),
+        }).collect()
+    }
+
+    fn format_part(part: &Part, activity: &Activity, photo: Option<&Photo>) -> String {
+        match (part, photo) {
+            (Part::Text(text), _) => text.clone(),
+            (Part::BrancaUpper, _) => activity.branca.to_uppercase(),
+            (Part::Branca, _) => activity.branca.clone(),
+            (Part::Title, _) => activity.title.clone(),
+            (Part::Number(digits), Some(photo)) => format!("{:0>width$}", photo.number, width = *digits),
+            (Part::Original, Some(photo)) => photo.original.clone(),
+            (Part::Photographer, Some(photo)) => photo.photographer.clone(),
+            (_, None) | (Part::FullYear | Part::Year | Part::Month | Part::Day, _) => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Naming {
+    folder: Template,
+    file: Template,
+}
+
+impl Naming {
+    /// Checks both templates, the error is meant to be shown to the user.
+    pub fn new(folder_template: &str, file_template: &str) -> Result<Naming, String> {
+        let folder = Template::parse(folder_template).map_err(|e| format!("Modello della cartella `{}`: {}", folder_template, e))?;
+        let file = Template::parse(file_template).map_err(|e| format!("Modello dei file `{}`: {}", file_template, e))?;
+
+        if folder.parts.iter().any(Part::is_per_photo) {
+            return Err(format!("Modello della cartella `{}`: non può contenere `{{n}}`, `{{originale}}` o `{{fotografo}}`, che cambiano da foto a foto.", folder_template));
+        }
+        if !file.parts.iter().any(|part| matches!(part, Part::Number(_))) {
+            return Err(format!("Modello dei file `{}`: deve contenere `{{n}}`, altrimenti più foto potrebbero avere lo stesso nome.", file_template));
+        }
+
+        Ok(Naming { folder, file })
+    }
+
+    /// Name of the folder, an error if it would be empty (or made only of dots).
+    pub fn folder(&self, activity: &Activity) -> Result<String, String> {
+        let name = self.folder.format(activity, None);
+        if name.trim_matches('.').trim().is_empty() {
+            return Err(String::from("Il nome della cartella risulterebbe vuoto, controllare i `Dati`."));
+        }
+        Ok(name)
+    }
+
+    pub fn file(&self, activity: &Activity, photo: &Photo) -> String {
+        format!("{}{}", self.file.format(activity, Some(photo)), FILE_EXTENSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(date: (i32, u32, u32), end_date: Option<(i32, u32, u32)>) -> Activity {
+        let day = |(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        Activity { date: day(date), end_date: end_date.map(day), branca: String::from("Esplo"), title: String::from("UscitaAlLago") }
+    }
+
+    fn photo(number: usize) -> Photo {
+        Photo { number, original: String::from("IMG_0042"), photographer: String::from("MarioRossi") }
+    }
+
+    #[test]
+    fn default_templates() {
+        let naming = Naming::new(DEFAULT_FOLDER_TEMPLATE, DEFAULT_FILE_TEMPLATE).unwrap();
+        let activity = activity((2024, 8, 1), None);
+        assert_eq!(naming.folder(&activity).unwrap(), "240801_ESPLO_UscitaAlLago");
+        assert_eq!(naming.file(&activity, &photo(7)), "240801_ESPLO_UscitaAlLago_007.JPG");
+    }
+
+    #[test]
+    fn number_padding() {
+        let naming = Naming::new("{titolo}", "{n}-{n:3}-{n:1}").unwrap();
+        let activity = activity((2024, 8, 1), None);
+        assert_eq!(naming.file(&activity, &photo(42)), "42-042-42.JPG");
+        assert_eq!(naming.file(&activity, &photo(1234)), "1234-1234-1234.JPG");
+    }
+
+    #[test]
+    fn dates_of_more_days() {
+        let activity = activity((2024, 8, 1), Some((2024, 8, 10)));
+        let naming = Naming::new("{aa}{mm}{gg}_{titolo}", "{aaaa}.{mm}.{gg} {n}").unwrap();
+        assert_eq!(naming.folder(&activity).unwrap(), "240801-240810_UscitaAlLago");
+        assert_eq!(naming.file(&activity, &photo(1)), "2024.08.01-2024.08.10 1.JPG");
+        // separated by words, the placeholders are two different dates
+        let naming = Naming::new("{gg} al {mm}", "{n}").unwrap();
+        assert_eq!(naming.folder(&activity).unwrap(), "01-10 al 08-08");
+    }
+
+    #[test]
+    fn per_photo_placeholders() {
+        let naming = Naming::new("{branca}", "{originale}_{fotografo}_{n}").unwrap();
+        let activity = activity((2024, 8, 1), None);
+        assert_eq!(naming.folder(&activity).unwrap(), "Esplo");
+        assert_eq!(naming.file(&activity, &photo(3)), "IMG_0042_MarioRossi_3.JPG");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(Naming::new("", "{n}").is_err());
+        assert!(Naming::new("{titolo}", "{titolo}").is_err());
+        assert!(Naming::new("{titolo}_{n}", "{n}").is_err());
+        assert!(Naming::new("{titolo", "{n}").is_err());
+        assert!(Naming::new("titolo}", "{n}").is_err());
+        assert!(Naming::new("{anno}", "{n}").is_err());
+        assert!(Naming::new("a/b", "{n}").is_err());
+        assert!(Naming::new("{titolo}", "{n:0}").is_err());
+        assert!(Naming::new("{titolo}", "{n:10}").is_err());
+    }
+
+    #[test]
+    fn empty_folder_name() {
+        let naming = Naming::new("{titolo}", "{n}").unwrap();
+        let activity = Activity { title: String::new(), ..activity((2024, 8, 1), None) };
+        assert!(naming.folder(&activity).is_err());
+    }
+
+    #[test]
+    fn activity_days() {
+        let activity = activity((2024, 8, 1), Some((2024, 8, 3)));
+        assert!(activity.contains(NaiveDate::from_ymd_opt(2024, 8, 3).unwrap()));
+        assert!(!activity.contains(NaiveDate::from_ymd_opt(2024, 8, 4).unwrap()));
+    }
+}
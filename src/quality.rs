@@ -0,0 +1,109 @@
+use image::DynamicImage;
+
+// Laplacian variance measured on the scaled preview, below this the photo is considered blurry
+const BLUR_THRESHOLD: f64 = 80.0;
+
+const DARK_LUMA: u8 = 24;
+const BRIGHT_LUMA: u8 = 235;
+const UNDEREXPOSED_MEAN: f64 = 50.0;
+const OVEREXPOSED_MEAN: f64 = 205.0;
+// fraction of clipped pixels that is enough to flag the exposure
+const CLIPPED_FRACTION: f64 = 0.4;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quality {
+    pub sharpness: f64,
+    pub mean_luma: f64,
+    pub blurry: bool,
+    pub underexposed: bool,
+    pub overexposed: bool,
+    pub low_resolution: bool,
+}
+
+impl Quality {
+    /// `original_size` is the size of the decoded file, `scaled` the already resized image
+    /// that will be saved (so that sharpness values are comparable between photos).
+    pub fn analyze(original_size: (u32, u32), scaled: &DynamicImage, small_dim: u32, big_dim: u32) -> Quality {
+        let luma = scaled.to_luma8();
+        let (width, height) = luma.dimensions();
+
+        let mut histogram = [0u64; 256];
+        for p in luma.pixels() {
+            histogram[p.0[0] as usize] += 1;
+        }
+        let pixel_count = (width as u64 * height as u64).max(1) as f64;
+        let mean_luma = histogram.iter().enumerate().map(|(v, n)| v as f64 * *n as f64).sum::<f64>() / pixel_count;
+        let dark_fraction = histogram[..=DARK_LUMA as usize].iter().sum::<u64>() as f64 / pixel_count;
+        let bright_fraction = histogram[BRIGHT_LUMA as usize..].iter().sum::<u64>() as f64 / pixel_count;
+
+        let sharpness = laplacian_variance(&luma);
+
+        let (orig_small, orig_big) = if original_size.0 > original_size.1 {
+            (original_size.1, original_size.0)
+        } else {
+            (original_size.0, original_size.1)
+        };
+
+        Quality {
+            sharpness,
+            mean_luma,
+            blurry: sharpness < BLUR_THRESHOLD,
+            underexposed: mean_luma < UNDEREXPOSED_MEAN || dark_fraction > CLIPPED_FRACTION,
+            overexposed: mean_luma > OVEREXPOSED_MEAN || bright_fraction > CLIPPED_FRACTION,
+            low_resolution: orig_small < small_dim || orig_big < big_dim,
+        }
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.blurry || self.underexposed || self.overexposed || self.low_resolution
+    }
+
+    /// Short badge label and a longer description for each raised flag, with the measured value
+    /// so that it can be compared with the thresholds.
+    pub fn flags(&self) -> Vec<(&'static str, String)> {
+        let mut flags = vec![];
+        if self.blurry {
+            flags.push(("M", format!("mossa/sfocata (nitidezza {:.0} su almeno {:.0})", self.sharpness, BLUR_THRESHOLD)));
+        }
+        if self.underexposed {
+            flags.push(("S", format!("sottoesposta (luminosità media {:.0})", self.mean_luma)));
+        }
+        if self.overexposed {
+            flags.push(("C", format!("sovraesposta (luminosità media {:.0})", self.mean_luma)));
+        }
+        if self.low_resolution {
+            flags.push(("R", String::from("bassa risoluzione")));
+        }
+        flags
+    }
+}
+
+fn laplacian_variance(luma: &image::GrayImage) -> f64 {
+    let (width, height) = luma.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let raw = luma.as_raw();
+    let w = width as usize;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    let mut count = 0.0;
+
+    for y in 1..height as usize - 1 {
+        for x in 1..w - 1 {
+            let c = raw[y * w + x] as f64;
+            let lap = raw[(y - 1) * w + x] as f64
+                + raw[(y + 1) * w + x] as f64
+                + raw[y * w + x - 1] as f64
+                + raw[y * w + x + 1] as f64
+                - 4.0 * c;
+            sum += lap;
+            sum_sq += lap * lap;
+            count += 1.0;
+        }
+    }
+
+    let mean = sum / count;
+    sum_sq / count - mean * mean
+}
@@ -0,0 +1,245 @@
+// Areas of a photo hidden before it is saved, for the faces of children without consent to be
+// photographed or for name tags. The areas are stored in fractions of the unrotated photo, so that
+// they stay in place when it is turned and fit both the normal and the HD size; they are applied
+// to the preview, which already has the size of the saved photo.
+
+use image::{imageops, DynamicImage, RgbImage};
+
+// size of the blocks of the pixelation and strength of the blur, in fractions of the longer side
+// of the photo so that a redacted area looks the same at every size
+const PIXELATE_BLOCKS: u32 = 40;
+const BLUR_FRACTION: f32 = 1.0 / 40.0;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum Shape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum Effect {
+    #[default]
+    Pixelate,
+    Blur,
+}
+
+impl Shape {
+    pub fn toggled(self) -> Shape {
+        match self {
+            Shape::Rectangle => Shape::Ellipse,
+            Shape::Ellipse => Shape::Rectangle,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Rectangle => "rettangolo",
+            Shape::Ellipse => "ellisse",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Shape> {
+        [Shape::Rectangle, Shape::Ellipse].iter().copied().find(|shape| shape.name() == name)
+    }
+}
+
+impl Effect {
+    pub fn toggled(self) -> Effect {
+        match self {
+            Effect::Pixelate => Effect::Blur,
+            Effect::Blur => Effect::Pixelate,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Pixelate => "pixel",
+            Effect::Blur => "sfocatura",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Effect> {
+        [Effect::Pixelate, Effect::Blur].iter().copied().find(|effect| effect.name() == name)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Redaction {
+    pub shape: Shape,
+    pub effect: Effect,
+    // bounds of the area, from 0.0 to 1.0 on both axes
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Redaction {
+    /// The same area on the photo turned by `rotation` quarter turns clockwise.
+    pub fn rotated(&self, rotation: u8) -> Redaction {
+        let mut area = *self;
+        for _ in 0..rotation % 4 {
+            area = Redaction { x: 1.0 - area.y - area.height, y: area.x, width: area.height, height: area.width, ..area };
+        }
+        area
+    }
+
+    /// The area on the unrotated photo of one drawn on the photo turned by `rotation`.
+    pub fn unrotated(&self, rotation: u8) -> Redaction {
+        self.rotated(4 - rotation % 4)
+    }
+
+    /// Whether the point (in fractions of the photo) is inside the area.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let (dx, dy) = ((x - self.x) / self.width, (y - self.y) / self.height);
+        match self.shape {
+            Shape::Rectangle => (0.0..=1.0).contains(&dx) && (0.0..=1.0).contains(&dy),
+            Shape::Ellipse => (dx - 0.5).powi(2) + (dy - 0.5).powi(2) <= 0.25,
+        }
+    }
+
+    // pixels covered by the bounds in an image of `width`x`height`, from the first to past the last
+    fn pixel_bounds(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let to_pixel = |fraction: f32, size: u32| ((fraction.clamp(0.0, 1.0) * size as f32).round() as u32).min(size);
+        (
+            to_pixel(self.x, width),
+            to_pixel(self.y, height),
+            to_pixel(self.x + self.width, width),
+            to_pixel(self.y + self.height, height),
+        )
+    }
+}
+
+/// `image` with the areas hidden, for the photo turned by `rotation` quarter turns clockwise.
+pub fn apply(image: &DynamicImage, redactions: &[Redaction], rotation: u8) -> DynamicImage {
+    let mut rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let longer_side = width.max(height);
+
+    for redaction in redactions.iter().map(|redaction| redaction.rotated(rotation)) {
+        let (x0, y0, x1, y1) = redaction.pixel_bounds(width, height);
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+        let covered = |x: u32, y: u32| redaction.contains((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32);
+
+        match redaction.effect {
+            Effect::Pixelate => {
+                let block = (longer_side / PIXELATE_BLOCKS).max(4);
+                for block_y in (y0..y1).step_by(block as usize) {
+                    for block_x in (x0..x1).step_by(block as usize) {
+                        let (block_x1, block_y1) = ((block_x + block).min(x1), (block_y + block).min(y1));
+                        let color = average(&rgb, block_x, block_y, block_x1, block_y1);
+                        for y in block_y..block_y1 {
+                            for x in (block_x..block_x1).filter(|x| covered(*x, y)) {
+                                rgb.put_pixel(x, y, color);
+                            }
+                        }
+                    }
+                }
+            },
+            Effect::Blur => {
+                let sigma = (longer_side as f32 * BLUR_FRACTION).max(2.0);
+                let area = imageops::crop_imm(&rgb, x0, y0, x1 - x0, y1 - y0).to_image();
+                let blurred = imageops::fast_blur(&area, sigma);
+                for y in y0..y1 {
+                    for x in (x0..x1).filter(|x| covered(*x, y)) {
+                        rgb.put_pixel(x, y, *blurred.get_pixel(x - x0, y - y0));
+                    }
+                }
+            },
+        }
+    }
+
+    DynamicImage::ImageRgb8(rgb)
+}
+
+// average color of the pixels from (`x0`, `y0`) to past (`x1`, `y1`)
+fn average(rgb: &RgbImage, x0: u32, y0: u32, x1: u32, y1: u32) -> image::Rgb<u8> {
+    let mut sums = [0u64; 3];
+    for y in y0..y1 {
+        for x in x0..x1 {
+            for (sum, channel) in sums.iter_mut().zip(rgb.get_pixel(x, y).0) {
+                *sum += channel as u64;
+            }
+        }
+    }
+    let count = ((x1 - x0) * (y1 - y0)).max(1) as u64;
+    image::Rgb(sums.map(|sum| (sum / count) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: f32, y: f32, width: f32, height: f32) -> Redaction {
+        Redaction { shape: Shape::Rectangle, effect: Effect::Pixelate, x, y, width, height }
+    }
+
+    fn assert_same(a: Redaction, b: Redaction) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(a.x, b.x) && close(a.y, b.y) && close(a.width, b.width) && close(a.height, b.height), "{:?} != {:?}", a, b);
+        assert_eq!((a.shape, a.effect), (b.shape, b.effect));
+    }
+
+    // black and white pixels, so that the pixelated ones become gray
+    fn checkerboard(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| if (x + y) % 2 == 0 { image::Rgb([0; 3]) } else { image::Rgb([255; 3]) }))
+    }
+
+    // whether only the pixels from (`x0`, `y0`) to past (`x1`, `y1`) were changed
+    fn only_changed(original: &DynamicImage, redacted: &DynamicImage, (x0, y0, x1, y1): (u32, u32, u32, u32)) -> bool {
+        let (original, redacted) = (original.to_rgb8(), redacted.to_rgb8());
+        original.enumerate_pixels().all(|(x, y, pixel)| {
+            let inside = (x0..x1).contains(&x) && (y0..y1).contains(&y);
+            (redacted.get_pixel(x, y) != pixel) == inside
+        })
+    }
+
+    #[test]
+    fn rotated_and_back() {
+        let drawn = area(0.1, 0.2, 0.3, 0.15);
+        for rotation in 0..4 {
+            assert_same(drawn.rotated(rotation).unrotated(rotation), drawn);
+            assert_same(drawn.unrotated(rotation).rotated(rotation), drawn);
+        }
+    }
+
+    #[test]
+    fn four_quarter_turns() {
+        let drawn = area(0.1, 0.2, 0.3, 0.15);
+        let turned = (0..4).fold(drawn, |area, _| area.rotated(1));
+        assert_same(turned, drawn);
+        assert_eq!(drawn.rotated(4), drawn);
+        // a quarter turn clockwise moves the top left corner to the top right
+        assert_same(area(0.0, 0.0, 0.25, 0.5).rotated(1), area(0.5, 0.0, 0.5, 0.25));
+    }
+
+    #[test]
+    fn pixels_of_the_area() {
+        let image = checkerboard(16, 16);
+        let redacted = apply(&image, &[area(0.25, 0.25, 0.5, 0.25)], 0);
+        assert!(only_changed(&image, &redacted, (4, 4, 12, 8)));
+        assert!(redacted.to_rgb8().enumerate_pixels().filter(|(x, y, _)| (4..12).contains(x) && (4..8).contains(y)).all(|(_, _, pixel)| pixel.0 == [127; 3]));
+    }
+
+    #[test]
+    fn pixels_of_the_rotated_photo() {
+        // 16x8 photo turned once: the area in its top left corner ends in the top right one
+        let image = checkerboard(8, 16);
+        let redacted = apply(&image, &[area(0.0, 0.0, 0.25, 0.5)], 1);
+        assert!(only_changed(&image, &redacted, (4, 0, 8, 4)));
+    }
+
+    #[test]
+    fn ellipse_leaves_the_corners() {
+        let image = checkerboard(16, 16);
+        let ellipse = Redaction { shape: Shape::Ellipse, ..area(0.0, 0.0, 1.0, 1.0) };
+        let (original, redacted) = (image.to_rgb8(), apply(&image, &[ellipse], 0).to_rgb8());
+        for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+            assert_eq!(redacted.get_pixel(x, y), original.get_pixel(x, y));
+        }
+        assert_ne!(redacted.get_pixel(8, 8), original.get_pixel(8, 8));
+    }
+}
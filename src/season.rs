@@ -0,0 +1,161 @@
+// On the server the folder of each activity goes in the folder of its scouting year, which starts
+// in the same month every year. The name of that folder comes from a template with placeholders:
+//
+//     {inizio} {fine}        first and last calendar year of the scouting year
+//     {inizio:2} {fine:2}    the same, with two digits
+//
+// Without a template the folders of the activities go directly in the login folder of the server.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::naming::FORBIDDEN_CHARS;
+
+pub const DEFAULT_START_MONTH: u32 = 8;
+pub const DEFAULT_FOLDER_TEMPLATE: &str = "{inizio}-{fine}";
+
+#[derive(Debug, Clone)]
+pub struct Season {
+    // first month of the scouting year, from 1 (January) to 12
+    pub start_month: u32,
+    // `None` for the flat layout
+    pub folder_template: Option<String>,
+}
+
+impl Default for Season {
+    fn default() -> Self {
+        Season {
+            start_month: DEFAULT_START_MONTH,
+            folder_template: Some(String::from(DEFAULT_FOLDER_TEMPLATE)),
+        }
+    }
+}
+
+// placeholders and the text they stand for, given the first and last year
+fn placeholders(start: i32, end: i32) -> [(&'static str, String); 4] {
+    [
+        ("{inizio}", format!("{:04}", start)),
+        ("{fine}", format!("{:04}", end)),
+        ("{inizio:2}", format!("{:02}", start % 100)),
+        ("{fine:2}", format!("{:02}", end % 100)),
+    ]
+}
+
+impl Season {
+    /// Checks a folder template, the error is meant to be shown to the user.
+    pub fn check_template(template: &str) -> Result<(), String> {
+        let literal = placeholders(0, 0).iter().fold(template.to_owned(), |text, (placeholder, _)| text.replace(placeholder, ""));
+        if let Some(start) = literal.find('{') {
+            let name = literal[start..].split('}').next().unwrap_or_default();
+            return Err(format!("il segnaposto `{}}}` non esiste.", name));
+        }
+        if let Some(c) = literal.chars().find(|c| FORBIDDEN_CHARS.contains(*c) || c.is_control() || *c == '}') {
+            return Err(format!("contiene `{}`, che non è ammesso nei nomi di cartella.", c));
+        }
+        if template.trim().is_empty() {
+            return Err(String::from("è vuoto."));
+        }
+        Ok(())
+    }
+
+    /// Reads `"mese_inizio_anno_scout"` and `"cartella_anno_scout"` from the settings, the missing
+    /// ones keep their default value. The error is meant to be shown to the user.
+    pub fn from_json(settings: &json::JsonValue) -> Result<Season, String> {
+        let mut season = Season::default();
+
+        let start_month = &settings["mese_inizio_anno_scout"];
+        if !start_month.is_null() {
+            match start_month.as_u32() {
+                Some(month) if (1..=12).contains(&month) => season.start_month = month,
+                _ => return Err(String::from("\"mese_inizio_anno_scout\" deve essere un numero da 1 a 12.")),
+            }
+        }
+
+        let folder = &settings["cartella_anno_scout"];
+        if !folder.is_null() {
+            match (folder.as_bool(), folder.as_str()) {
+                (Some(false), _) => season.folder_template = None,
+                (_, Some(template)) => {
+                    Season::check_template(template).map_err(|e| format!("\"cartella_anno_scout\" non è valido: {}", e))?;
+                    season.folder_template = Some(template.to_owned());
+                },
+                _ => return Err(String::from("\"cartella_anno_scout\" deve essere una stringa oppure `false`.")),
+            }
+        }
+
+        Ok(season)
+    }
+
+    // first and last calendar year of the scouting year of `date`
+    fn years(&self, date: NaiveDate) -> (i32, i32) {
+        let start = if date.month() < self.start_month { date.year() - 1 } else { date.year() };
+        let end = if self.start_month == 1 { start } else { start + 1 };
+        (start, end)
+    }
+
+    /// Folder of the scouting year of `date`, `None` with the flat layout.
+    pub fn folder(&self, date: NaiveDate) -> Option<String> {
+        let template = self.folder_template.as_ref()?;
+        let (start, end) = self.years(date);
+        Some(placeholders(start, end).iter().fold(template.clone(), |text, (placeholder, value)| text.replace(placeholder, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn season(start_month: u32, template: &str) -> Season {
+        Season { start_month, folder_template: Some(template.to_owned()) }
+    }
+
+    #[test]
+    fn september_to_august() {
+        let season = season(9, DEFAULT_FOLDER_TEMPLATE);
+        assert_eq!(season.folder(day(2024, 8, 31)).as_deref(), Some("2023-2024"));
+        assert_eq!(season.folder(day(2024, 9, 1)).as_deref(), Some("2024-2025"));
+        assert_eq!(season.folder(day(2024, 12, 31)).as_deref(), Some("2024-2025"));
+    }
+
+    #[test]
+    fn january_to_august_in_the_previous_season() {
+        let season = Season::default();
+        for month in 1..DEFAULT_START_MONTH {
+            assert_eq!(season.years(day(2025, month, 15)), (2024, 2025));
+        }
+        assert_eq!(season.years(day(2025, DEFAULT_START_MONTH, 1)), (2025, 2026));
+        // starting in January, the scouting year is the calendar one
+        assert_eq!(Season { start_month: 1, ..Season::default() }.years(day(2025, 1, 1)), (2025, 2025));
+    }
+
+    #[test]
+    fn placeholders_with_two_digits() {
+        assert_eq!(season(9, "AS{inizio:2}{fine:2}").folder(day(2000, 3, 1)).as_deref(), Some("AS9900"));
+        assert_eq!(Season { folder_template: None, ..Season::default() }.folder(day(2024, 9, 1)), None);
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(Season::check_template("{inizio}-{fine}").is_ok());
+        assert!(Season::check_template("Anno {inizio:2}").is_ok());
+        assert!(Season::check_template("{anno}").is_err());
+        assert!(Season::check_template("{inizio").is_err());
+        assert!(Season::check_template("{inizio}}").is_err());
+        assert!(Season::check_template("{inizio}/{fine}").is_err());
+        assert!(Season::check_template("  ").is_err());
+    }
+
+    #[test]
+    fn settings() {
+        let parse = |text: &str| Season::from_json(&json::parse(text).unwrap());
+        let season = parse(r#"{ "mese_inizio_anno_scout": 9, "cartella_anno_scout": "{inizio}" }"#).unwrap();
+        assert_eq!((season.start_month, season.folder_template.as_deref()), (9, Some("{inizio}")));
+        assert_eq!(parse(r#"{ "cartella_anno_scout": false }"#).unwrap().folder_template, None);
+        assert!(parse(r#"{ "mese_inizio_anno_scout": 13 }"#).is_err());
+        assert!(parse(r#"{ "cartella_anno_scout": "{anno}" }"#).is_err());
+        assert!(parse(r#"{ "cartella_anno_scout": true }"#).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// Every line of a `fototpm-imglist_*.txt` file is the path of an image, optionally followed by
+// tab separated `key=value` attributes that restore what was done on the photo:
+//
+//     /home/user/foto/IMG_0001.JPG	stelle=3	scelta=si	rotazione=90	privata=si
+//
+// Private photos (`privata=si`) can be kept locally but are never uploaded.
+//
+// The hidden areas are separated by `;`, each one with its shape, its effect and its bounds in
+// fractions of the unrotated photo:
+//
+//     oscura=ellisse,pixel,0.4120,0.1800,0.0950,0.1300;rettangolo,sfocatura,0.1,0.7,0.2,0.05
+
+use crate::redact::{Effect, Redaction, Shape};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum PickFlag {
+    #[default]
+    Unflagged,
+    Pick,
+    Reject,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImgMeta {
+    pub rating: u8,
+    pub flag: PickFlag,
+    // quarter turns clockwise
+    pub rotation: u8,
+    // areas hidden in the saved photo
+    pub redactions: Vec<Redaction>,
+    // not to be published, see `cartella_private` in `config.rs`
+    pub private: bool,
+}
+
+pub const MAX_RATING: u8 = 5;
+
+fn parse_redaction(text: &str) -> Option<Redaction> {
+    let mut fields = text.split(',').map(str::trim);
+    let shape = Shape::from_name(fields.next()?)?;
+    let effect = Effect::from_name(fields.next()?)?;
+    let mut bounds = [0.0; 4];
+    for bound in bounds.iter_mut() {
+        *bound = fields.next()?.parse::<f32>().ok().filter(|bound| (0.0..=1.0).contains(bound))?;
+    }
+    if fields.next().is_some() {
+        return None;
+    }
+    let [x, y, width, height] = bounds;
+    Some(Redaction { shape, effect, x, y, width, height })
+}
+
+fn format_redaction(redaction: &Redaction) -> String {
+    format!("{},{},{:.4},{:.4},{:.4},{:.4}", redaction.shape.name(), redaction.effect.name(), redaction.x, redaction.y, redaction.width, redaction.height)
+}
+
+pub fn parse_line(line: &str) -> (&str, ImgMeta) {
+    let mut parts = line.split('\t');
+    let path = parts.next().unwrap_or_default();
+    let mut meta = ImgMeta::default();
+
+    for attribute in parts {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        match key.trim() {
+            "stelle" => {
+                meta.rating = value.trim().parse::<u8>().unwrap_or(0).min(MAX_RATING);
+            },
+            "rotazione" => {
+                meta.rotation = (value.trim().parse::<u32>().unwrap_or(0) / 90 % 4) as u8;
+            },
+            "oscura" => {
+                for area in value.split(';').filter(|area| !area.trim().is_empty()) {
+                    match parse_redaction(area) {
+                        Some(redaction) => meta.redactions.push(redaction),
+                        None => eprintln!("[WARNING]: Zona da oscurare non valida `{}` per `{}`.", area, path),
+                    }
+                }
+            },
+            "privata" => {
+                meta.private = value.trim() == "si";
+            },
+            "scelta" => {
+                meta.flag = match value.trim() {
+                    "si" => PickFlag::Pick,
+                    "no" => PickFlag::Reject,
+                    _ => PickFlag::Unflagged,
+                };
+            },
+            _ => {
+                eprintln!("[WARNING]: Attributo sconosciuto `{}` per `{}`.", attribute, path);
+            },
+        }
+    }
+
+    (path, meta)
+}
+
+pub fn format_line(path: &str, meta: &ImgMeta) -> String {
+    let mut line = path.to_owned();
+    if meta.rating > 0 {
+        line.push_str(&format!("\tstelle={}", meta.rating));
+    }
+    match meta.flag {
+        PickFlag::Unflagged => {},
+        PickFlag::Pick => line.push_str("\tscelta=si"),
+        PickFlag::Reject => line.push_str("\tscelta=no"),
+    }
+    if meta.rotation > 0 {
+        line.push_str(&format!("\trotazione={}", meta.rotation as u32 * 90));
+    }
+    if meta.private {
+        line.push_str("\tprivata=si");
+    }
+    if !meta.redactions.is_empty() {
+        let areas = meta.redactions.iter().map(format_redaction).collect::<Vec<_>>();
+        line.push_str(&format!("\toscura={}", areas.join(";")));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_path() {
+        let (path, meta) = parse_line("/home/user/foto/IMG 0001.JPG");
+        assert_eq!(path, "/home/user/foto/IMG 0001.JPG");
+        assert_eq!(meta, ImgMeta::default());
+        assert_eq!(format_line(path, &meta), "/home/user/foto/IMG 0001.JPG");
+    }
+
+    #[test]
+    fn round_trip() {
+        let meta = ImgMeta {
+            rating: 3,
+            flag: PickFlag::Reject,
+            rotation: 3,
+            redactions: vec![
+                Redaction { shape: Shape::Ellipse, effect: Effect::Pixelate, x: 0.412, y: 0.18, width: 0.095, height: 0.13 },
+                Redaction { shape: Shape::Rectangle, effect: Effect::Blur, x: 0.1, y: 0.7, width: 0.2, height: 0.05 },
+            ],
+            private: true,
+        };
+        let line = format_line("C:\\foto\\IMG_0001.JPG", &meta);
+        assert_eq!(line, "C:\\foto\\IMG_0001.JPG\tstelle=3\tscelta=no\trotazione=270\tprivata=si\toscura=ellisse,pixel,0.4120,0.1800,0.0950,0.1300;rettangolo,sfocatura,0.1000,0.7000,0.2000,0.0500");
+        assert_eq!(parse_line(&line), ("C:\\foto\\IMG_0001.JPG", meta));
+    }
+
+    #[test]
+    fn values_are_normalized() {
+        let (_, meta) = parse_line("a.jpg\tstelle=9\trotazione=450\tscelta=forse\tprivata=no");
+        assert_eq!(meta.rating, MAX_RATING);
+        assert_eq!(meta.rotation, 1);
+        assert_eq!(meta.flag, PickFlag::Unflagged);
+        assert!(!meta.private);
+
+        let (_, meta) = parse_line("a.jpg\tstelle=tre\trotazione=-90");
+        assert_eq!((meta.rating, meta.rotation), (0, 0));
+    }
+
+    #[test]
+    fn invalid_attributes_are_skipped() {
+        let (path, meta) = parse_line("a.jpg\tcolore=rosso\tscelta=si\toscura=ellisse,pixel,0.1,0.1,0.2;stella,pixel,0,0,1,1;rettangolo,pixel,0,0,1.5,1;;rettangolo,pixel,0,0,1,1");
+        assert_eq!(path, "a.jpg");
+        assert_eq!(meta.flag, PickFlag::Pick);
+        assert_eq!(meta.redactions, [Redaction { shape: Shape::Rectangle, effect: Effect::Pixelate, x: 0.0, y: 0.0, width: 1.0, height: 1.0 }]);
+    }
+}
@@ -0,0 +1,116 @@
+// Folder and file names are built from what is typed in the `Dati` tab, so they must be safe on
+// every filesystem and FTP server: accented letters are transliterated, words are joined following
+// the chosen style and every other character is dropped.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlugStyle {
+    // `Uscita al lago` -> `UscitaAlLago`
+    CamelCase,
+    // `Uscita al lago` -> `Uscita-al-lago`
+    Hyphens,
+}
+
+impl SlugStyle {
+    pub fn from_name(name: &str) -> Option<SlugStyle> {
+        match name.to_lowercase().as_str() {
+            "camelcase" => Some(SlugStyle::CamelCase),
+            "trattini" => Some(SlugStyle::Hyphens),
+            _ => None,
+        }
+    }
+}
+
+// ASCII spelling of the letters that can appear in Italian (and most western) text
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ä' | 'ã' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'À' | 'Á' | 'Â' | 'Ä' | 'Ã' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ò' | 'ó' | 'ô' | 'ö' | 'õ' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Ö' | 'Õ' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ý' | 'ÿ' => "y",
+        'Ý' | 'Ÿ' => "Y",
+        'ł' => "l",
+        'Ł' => "L",
+        'đ' | 'ď' => "d",
+        'Đ' | 'Ď' => "D",
+        'ß' => "ss",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        _ => return None,
+    })
+}
+
+// words of `s` in ASCII, split on anything that is not a letter or a digit
+fn words(s: &str) -> Vec<String> {
+    let mut words = vec![String::new()];
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            words.last_mut().unwrap().push(c);
+        } else if let Some(ascii) = transliterate(c) {
+            words.last_mut().unwrap().push_str(ascii);
+        } else if !words.last().unwrap().is_empty() {
+            words.push(String::new());
+        }
+    }
+    words.retain(|w| !w.is_empty());
+    words
+}
+
+/// Returns `s` as a name safe for files and folders, at most `max_len` characters long.
+pub fn slug(s: &str, style: SlugStyle, max_len: usize) -> String {
+    let mut slug = match style {
+        SlugStyle::CamelCase => words(s)
+            .iter()
+            .map(|w| w[..1].to_uppercase() + &w[1..])
+            .collect::<String>(),
+        SlugStyle::Hyphens => words(s).join("-"),
+    };
+
+    // only ASCII is left, so characters and bytes are the same
+    slug.truncate(max_len);
+    slug.trim_end_matches('-').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles() {
+        assert_eq!(slug("Uscita al lago", SlugStyle::CamelCase, 40), "UscitaAlLago");
+        assert_eq!(slug("Uscita al lago", SlugStyle::Hyphens, 40), "Uscita-al-lago");
+        assert_eq!(SlugStyle::from_name("Trattini"), Some(SlugStyle::Hyphens));
+        assert_eq!(SlugStyle::from_name("spazi"), None);
+    }
+
+    #[test]
+    fn transliterates_and_drops_the_rest() {
+        assert_eq!(slug("Città, perché così?", SlugStyle::CamelCase, 40), "CittaPercheCosi");
+        assert_eq!(slug("Straße & Œuvre", SlugStyle::Hyphens, 40), "Strasse-OEuvre");
+        assert_eq!(slug("  campo 2024 / 25  ", SlugStyle::Hyphens, 40), "campo-2024-25");
+        assert_eq!(slug("🏕️ 日本", SlugStyle::CamelCase, 40), "");
+    }
+
+    #[test]
+    fn truncates_without_trailing_hyphens() {
+        assert_eq!(slug("Uscita al lago", SlugStyle::CamelCase, 8), "UscitaAl");
+        assert_eq!(slug("Uscita al lago", SlugStyle::Hyphens, 7), "Uscita");
+        assert_eq!(slug("Uscita al lago", SlugStyle::Hyphens, usize::MAX), "Uscita-al-lago");
+    }
+}
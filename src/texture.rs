@@ -0,0 +1,104 @@
+// Textures are created empty by raylib and then filled with a copy of the pixels, so no memory is
+// ever shared between the Rust image and the C side: raylib frees what it allocates and Rust
+// frees the rest.
+
+use image::DynamicImage;
+use raylib::prelude::*;
+
+// pixels of `image` in the layout of `PIXELFORMAT_UNCOMPRESSED_R8G8B8A8`, row by row from the top
+fn rgba_pixels(image: &DynamicImage) -> (i32, i32, Vec<u8>) {
+    let rgba = image.to_rgba8();
+    (rgba.width() as i32, rgba.height() as i32, rgba.into_raw())
+}
+
+pub fn texture_from_image(rl: &mut RaylibHandle, thread: &RaylibThread, image: &DynamicImage) -> Option<Texture2D> {
+    let (width, height, pixels) = rgba_pixels(image);
+
+    // `gen_image_color` always gives an R8G8B8A8 image, matching `rgba_pixels`
+    let blank = Image::gen_image_color(width, height, Color::BLANK);
+    let mut texture = match rl.load_texture_from_image(thread, &blank) {
+        Ok(texture) => texture,
+        Err(e) => {
+            eprintln!("[ERROR]: Impossibile creare la texture di un'immagine {}x{}: {}", width, height, e);
+            return None;
+        },
+    };
+
+    // a failed copy would leave a blank texture on screen
+    if let Err(e) = texture.update_texture(&pixels) {
+        eprintln!("[ERROR]: Impossibile copiare i pixel nella texture di un'immagine {}x{}: {}", width, height, e);
+        return None;
+    }
+    Some(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+
+    #[test]
+    fn rgb_gets_opaque_alpha() {
+        let mut img = RgbImage::new(2, 1);
+        img.put_pixel(0, 0, Rgb([10, 20, 30]));
+        img.put_pixel(1, 0, Rgb([40, 50, 60]));
+
+        let (width, height, pixels) = rgba_pixels(&DynamicImage::ImageRgb8(img));
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, vec![10, 20, 30, 255, 40, 50, 60, 255]);
+    }
+
+    #[test]
+    fn rgba_is_copied_unchanged() {
+        let mut img = RgbaImage::new(1, 2);
+        img.put_pixel(0, 0, Rgba([1, 2, 3, 4]));
+        img.put_pixel(0, 1, Rgba([5, 6, 7, 8]));
+
+        let (width, height, pixels) = rgba_pixels(&DynamicImage::ImageRgba8(img));
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(pixels, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn grayscale_is_expanded() {
+        let img = GrayImage::from_pixel(1, 1, Luma([77]));
+
+        let (_, _, pixels) = rgba_pixels(&DynamicImage::ImageLuma8(img));
+        assert_eq!(pixels, vec![77, 77, 77, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_is_narrowed() {
+        let img: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_pixel(1, 1, Rgb([0, 65535, 257 * 128]));
+
+        let (_, _, pixels) = rgba_pixels(&DynamicImage::ImageRgb16(img));
+        assert_eq!(pixels, vec![0, 255, 128, 255]);
+    }
+
+    #[test]
+    fn rows_are_top_to_bottom() {
+        let mut img = RgbImage::new(3, 2);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = Rgb([x as u8, y as u8, 0]);
+        }
+
+        let (width, height, pixels) = rgba_pixels(&DynamicImage::ImageRgb8(img));
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        let second_row = &pixels[3 * 4..];
+        assert_eq!(&second_row[..4], &[0, 1, 0, 255]);
+        assert_eq!(&second_row[8..12], &[2, 1, 0, 255]);
+    }
+
+    #[test]
+    fn rotated_dimensions() {
+        let mut img = RgbImage::new(3, 2);
+        img.put_pixel(0, 0, Rgb([9, 9, 9]));
+
+        // a quarter turn clockwise puts the top left corner on the top right
+        let (width, height, pixels) = rgba_pixels(&DynamicImage::ImageRgb8(img).rotate90());
+        assert_eq!((width, height), (2, 3));
+        assert_eq!(pixels.len(), 2 * 3 * 4);
+        assert_eq!(&pixels[4..8], &[9, 9, 9, 255]);
+        assert_eq!(&pixels[..4], &[0, 0, 0, 255]);
+    }
+}
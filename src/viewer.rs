@@ -0,0 +1,208 @@
+// Photos shown at full resolution in the whole window, one at a time or two to four side by side
+// to choose between similar shots. The originals are decoded again only while they are shown, and
+// the zoom and pan is kept in fractions of the image, so compared photos of different resolution
+// show the same detail.
+
+use std::path::Path;
+
+use raylib::ffi::CheckCollisionPointRec;
+use raylib::prelude::*;
+
+use crate::ImgData;
+
+const MAX_ZOOM: f32 = 16.0;
+const WHEEL_ZOOM_FACTOR: f32 = 1.25;
+
+/// Zoom and pan of an image inside some bounds, independent of the image size so that it can be
+/// shared by photos of different resolution.
+#[derive(Debug, Copy, Clone)]
+pub struct ZoomPan {
+    // 1.0 means that the image fits the bounds
+    pub zoom: f32,
+    // point of the image (0.0..=1.0 on both axes) drawn at the center of the bounds
+    pub center: Vector2,
+}
+
+impl Default for ZoomPan {
+    fn default() -> Self {
+        ZoomPan {
+            zoom: 1.0,
+            center: rvec2(0.5, 0.5),
+        }
+    }
+}
+
+impl ZoomPan {
+    fn fit_scale(image_size: Vector2, bounds: Rectangle) -> f32 {
+        (bounds.width / image_size.x).min(bounds.height / image_size.y)
+    }
+
+    /// Screen pixels per image pixel.
+    pub fn scale(&self, image_size: Vector2, bounds: Rectangle) -> f32 {
+        ZoomPan::fit_scale(image_size, bounds) * self.zoom
+    }
+
+    /// Where the whole image is drawn on screen.
+    pub fn image_rect(&self, image_size: Vector2, bounds: Rectangle) -> Rectangle {
+        let scale = self.scale(image_size, bounds);
+        rrect(
+            bounds.x + bounds.width / 2.0 - self.center.x * image_size.x * scale,
+            bounds.y + bounds.height / 2.0 - self.center.y * image_size.y * scale,
+            image_size.x * scale,
+            image_size.y * scale,
+        )
+    }
+
+    fn clamp(&mut self) {
+        self.zoom = self.zoom.clamp(1.0, MAX_ZOOM);
+        if self.zoom <= 1.0 {
+            self.center = rvec2(0.5, 0.5);
+        } else {
+            self.center.x = self.center.x.clamp(0.0, 1.0);
+            self.center.y = self.center.y.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Switches between fitting the bounds and one screen pixel per image pixel.
+    pub fn toggle_actual_size(&mut self, image_size: Vector2, bounds: Rectangle) {
+        let actual_size_zoom = 1.0 / ZoomPan::fit_scale(image_size, bounds);
+        if (self.zoom - actual_size_zoom).abs() < 0.01 || actual_size_zoom <= 1.0 {
+            self.zoom = 1.0;
+        } else {
+            self.zoom = actual_size_zoom;
+        }
+        self.clamp();
+    }
+
+    /// Mouse wheel zooms around the cursor, dragging with the left button pans.
+    pub fn update(&mut self, rl: &RaylibHandle, image_size: Vector2, bounds: Rectangle) {
+        let mouse = rl.get_mouse_position();
+        if !unsafe { CheckCollisionPointRec(mouse.into(), bounds.into()) } {
+            return;
+        }
+
+        let bounds_center = rvec2(bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0);
+
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel != 0.0 {
+            let scale = self.scale(image_size, bounds);
+            // point of the image under the cursor, that must stay there after zooming
+            let pointed = rvec2(
+                self.center.x + (mouse.x - bounds_center.x) / (scale * image_size.x),
+                self.center.y + (mouse.y - bounds_center.y) / (scale * image_size.y),
+            );
+
+            self.zoom *= WHEEL_ZOOM_FACTOR.powf(wheel);
+            self.zoom = self.zoom.clamp(1.0, MAX_ZOOM);
+
+            let scale = self.scale(image_size, bounds);
+            self.center = rvec2(
+                pointed.x - (mouse.x - bounds_center.x) / (scale * image_size.x),
+                pointed.y - (mouse.y - bounds_center.y) / (scale * image_size.y),
+            );
+        }
+
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            let delta = rl.get_mouse_delta();
+            let scale = self.scale(image_size, bounds);
+            self.center.x -= delta.x / (scale * image_size.x);
+            self.center.y -= delta.y / (scale * image_size.y);
+        }
+
+        self.clamp();
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, texture: &Texture2D, bounds: Rectangle) {
+        let image_size = rvec2(texture.width(), texture.height());
+        let dest = self.image_rect(image_size, bounds);
+        let mut s = d.begin_scissor_mode(bounds.x as i32, bounds.y as i32, bounds.width as i32, bounds.height as i32);
+        s.draw_texture_pro(texture, rrect(0.0, 0.0, image_size.x, image_size.y), dest, rvec2(0.0, 0.0), 0.0, Color::WHITE);
+    }
+}
+
+/// Loads the original file at full resolution, turned by `rotation` quarter turns clockwise.
+pub fn load_full_resolution(rl: &mut RaylibHandle, thread: &RaylibThread, path: &Path, rotation: u8) -> Option<Texture2D> {
+    let mut img = match Image::load_image(path.to_str()?) {
+        Ok(img) => img,
+        Err(e) => {
+            eprintln!("[ERROR]: Impossibile aprire `{}` a piena risoluzione: {}", path.display(), e);
+            return None;
+        },
+    };
+
+    for _ in 0..rotation % 4 {
+        img.rotate_cw();
+    }
+
+    match rl.load_texture_from_image(thread, &img) {
+        Ok(texture) => Some(texture),
+        Err(e) => {
+            eprintln!("[ERROR]: Impossibile mostrare `{}` a piena risoluzione: {}", path.display(), e);
+            None
+        },
+    }
+}
+
+/// Full window view of a single photo.
+pub struct Viewer {
+    // index in the list of images
+    pub image: usize,
+    // `None` if the original could not be loaded, the preview is shown instead
+    pub texture: Option<Texture2D>,
+    pub zoom_pan: ZoomPan,
+}
+
+impl Viewer {
+    pub fn open(rl: &mut RaylibHandle, thread: &RaylibThread, image: usize, path: &Path, rotation: u8, zoom_pan: ZoomPan) -> Viewer {
+        Viewer {
+            image,
+            texture: load_full_resolution(rl, thread, path, rotation),
+            zoom_pan,
+        }
+    }
+}
+
+const COMPARE_GAP: f32 = 8.0;
+
+/// Two to four photos side by side, all with the same zoom and pan.
+pub struct Compare {
+    // indices in the list of images
+    pub images: Vec<usize>,
+    pub textures: Vec<Option<Texture2D>>,
+    pub zoom_pan: ZoomPan,
+    // panel the keys act on
+    pub active: usize,
+}
+
+impl Compare {
+    pub const MIN_IMAGES: usize = 2;
+    pub const MAX_IMAGES: usize = 4;
+
+    pub fn open(rl: &mut RaylibHandle, thread: &RaylibThread, images: Vec<usize>, all_images: &[ImgData]) -> Compare {
+        let textures = images.iter()
+            .map(|i| load_full_resolution(rl, thread, &all_images[*i].path, all_images[*i].meta.rotation))
+            .collect();
+        Compare {
+            images,
+            textures,
+            zoom_pan: ZoomPan::default(),
+            active: 0,
+        }
+    }
+
+    /// Bounds of every panel: one row for two or three photos, a 2x2 grid for four.
+    pub fn panels(&self, area: Rectangle) -> Vec<Rectangle> {
+        let count = self.images.len().max(1);
+        let (columns, rows) = if count == 4 { (2, 2) } else { (count, 1) };
+        let width = (area.width - COMPARE_GAP * (columns - 1) as f32) / columns as f32;
+        let height = (area.height - COMPARE_GAP * (rows - 1) as f32) / rows as f32;
+        (0..count)
+            .map(|i| rrect(
+                area.x + (i % columns) as f32 * (width + COMPARE_GAP),
+                area.y + (i / columns) as f32 * (height + COMPARE_GAP),
+                width,
+                height,
+            ))
+            .collect()
+    }
+}
@@ -0,0 +1,279 @@
+// Logo and/or text of the group (like "© Tre Pini 2024") drawn over every saved photo. Both are
+// composed once, when the settings are read, into a single mark with the logo above the text; the
+// mark is then scaled and blended in each photo with the placement of its size, normal or HD:
+//
+//     "filigrana": {
+//         "logo": "logo.png",
+//         "testo": "© Tre Pini 2024",
+//         "normale": { "posizione": "basso-destra", "scala": 0.25, "opacita": 0.7, "margine": 0.03 },
+//         "hd": { "scala": 0.2 }
+//     }
+//
+// The scale is the width of the mark and the margin its distance from the edges, both in fractions
+// of the shorter side of the photo so that the mark looks the same in both orientations. The fields
+// missing in "hd" are the ones of "normale". The text is drawn with the font of the interface,
+// without a window, so that the console can use it too.
+
+use image::{imageops::{self, FilterType}, DynamicImage, RgbaImage};
+use raylib::ffi;
+
+use crate::gui::UI_FONT_DATA;
+
+// height of the text in the mark, big enough to be scaled down without losing detail
+const TEXT_BASE_SIZE: i32 = 128;
+// the text is white with a dark shadow, to be read on both light and dark photos
+const TEXT_SHADOW_OFFSET: i64 = TEXT_BASE_SIZE as i64 / 24;
+const TEXT_SHADOW_ALPHA: f32 = 0.6;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum Position {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+const POSITIONS: [Position; 5] = [Position::TopLeft, Position::TopRight, Position::BottomLeft, Position::BottomRight, Position::Center];
+
+impl Position {
+    pub fn name(self) -> &'static str {
+        match self {
+            Position::TopLeft => "alto-sinistra",
+            Position::TopRight => "alto-destra",
+            Position::BottomLeft => "basso-sinistra",
+            Position::BottomRight => "basso-destra",
+            Position::Center => "centro",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Position> {
+        POSITIONS.iter().copied().find(|position| position.name() == name)
+    }
+
+    // top left corner of a mark of `mark_width`x`mark_height` in a photo of `width`x`height`
+    fn origin(self, width: u32, height: u32, mark_width: u32, mark_height: u32, margin: i64) -> (i64, i64) {
+        let (free_x, free_y) = (width as i64 - mark_width as i64, height as i64 - mark_height as i64);
+        match self {
+            Position::TopLeft => (margin, margin),
+            Position::TopRight => (free_x - margin, margin),
+            Position::BottomLeft => (margin, free_y - margin),
+            Position::BottomRight => (free_x - margin, free_y - margin),
+            Position::Center => (free_x / 2, free_y / 2),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Placement {
+    pub position: Position,
+    // width of the mark, in fractions of the shorter side of the photo
+    pub scale: f32,
+    // from 0.0 (invisible) to 1.0
+    pub opacity: f32,
+    // distance from the edges, in fractions of the shorter side of the photo
+    pub margin: f32,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement { position: Position::default(), scale: 0.25, opacity: 0.7, margin: 0.03 }
+    }
+}
+
+impl Placement {
+    // the placement in `value`, with the missing fields taken from `defaults`
+    fn from_json(name: &str, value: &json::JsonValue, defaults: Placement) -> Result<Placement, String> {
+        if value.is_null() {
+            return Ok(defaults);
+        }
+        if !value.is_object() {
+            return Err(format!("\"{}\" deve essere un oggetto.", name));
+        }
+
+        let mut placement = defaults;
+        let position = &value["posizione"];
+        if !position.is_null() {
+            placement.position = position.as_str().and_then(Position::from_name).ok_or_else(|| {
+                let names = POSITIONS.iter().map(|position| format!("\"{}\"", position.name())).collect::<Vec<_>>().join(", ");
+                format!("la posizione di \"{}\" deve essere una tra {}.", name, names)
+            })?;
+        }
+
+        let number = |field: &str, valid: fn(f32) -> bool, range: &str| {
+            let number = &value[field];
+            match number.as_f32() {
+                _ if number.is_null() => Ok(None),
+                Some(number) if valid(number) => Ok(Some(number)),
+                _ => Err(format!("\"{}\" di \"{}\" deve essere un numero {}.", field, name, range)),
+            }
+        };
+        if let Some(scale) = number("scala", |scale| scale > 0.0 && scale <= 1.0, "maggiore di 0 e al massimo 1")? {
+            placement.scale = scale;
+        }
+        if let Some(opacity) = number("opacita", |opacity| (0.0..=1.0).contains(&opacity), "da 0 a 1")? {
+            placement.opacity = opacity;
+        }
+        if let Some(margin) = number("margine", |margin| (0.0..0.5).contains(&margin), "da 0 a meno di 0.5")? {
+            placement.margin = margin;
+        }
+
+        Ok(placement)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    // logo and text already composed, at their full size
+    mark: RgbaImage,
+    pub normal: Placement,
+    pub hd: Placement,
+}
+
+impl Watermark {
+    /// Reads the `"filigrana"` settings and loads the logo, the error is meant to be shown to the user.
+    pub fn from_json(value: &json::JsonValue) -> Result<Watermark, String> {
+        if !value.is_object() {
+            return Err(String::from("deve essere un oggetto."));
+        }
+
+        let logo = match (&value["logo"], value["logo"].as_str()) {
+            (logo, _) if logo.is_null() => None,
+            (_, Some(path)) => {
+                let logo = image::open(path).map_err(|e| format!("impossibile leggere il logo `{}`: {}", path, e))?;
+                Some(logo.to_rgba8())
+            },
+            _ => return Err(String::from("\"logo\" deve essere il percorso di un'immagine.")),
+        };
+        let text = match (&value["testo"], value["testo"].as_str().map(str::trim)) {
+            (text, _) if text.is_null() => None,
+            (_, Some(text)) if !text.is_empty() => Some(text),
+            _ => return Err(String::from("\"testo\" deve essere una stringa non vuota.")),
+        };
+
+        let text = match text {
+            Some(text) => Some(render_text(text).ok_or_else(|| format!("impossibile disegnare il testo `{}`.", text))?),
+            None => None,
+        };
+        let mark = match (logo, text) {
+            (Some(logo), Some(text)) => stack(&logo, &text),
+            (Some(logo), None) => logo,
+            (None, Some(text)) => text,
+            (None, None) => return Err(String::from("deve contenere almeno uno tra \"logo\" e \"testo\".")),
+        };
+
+        let normal = Placement::from_json("normale", &value["normale"], Placement::default())?;
+        let hd = Placement::from_json("hd", &value["hd"], normal)?;
+        Ok(Watermark { mark, normal, hd })
+    }
+
+    /// `image` with the mark blended in, with the placement of the HD size if `hd`.
+    pub fn apply(&self, image: &DynamicImage, hd: bool) -> DynamicImage {
+        let placement = if hd { self.hd } else { self.normal };
+        let mut rgb = image.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        let shorter_side = width.min(height) as f32;
+
+        let mark_width = ((shorter_side * placement.scale).round() as u32).max(1);
+        let mark_height = ((self.mark.height() as f32 * mark_width as f32 / self.mark.width() as f32).round() as u32).max(1);
+        let mark = imageops::resize(&self.mark, mark_width, mark_height, FilterType::Triangle);
+        let margin = (shorter_side * placement.margin).round() as i64;
+        let (x0, y0) = placement.position.origin(width, height, mark_width, mark_height, margin);
+
+        for (x, y, pixel) in mark.enumerate_pixels() {
+            let (x, y) = (x0 + x as i64, y0 + y as i64);
+            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                continue;
+            }
+            let alpha = pixel.0[3] as f32 / 255.0 * placement.opacity;
+            let photo = rgb.get_pixel_mut(x as u32, y as u32);
+            for (channel, mark_channel) in photo.0.iter_mut().zip(pixel.0) {
+                *channel = (*channel as f32 * (1.0 - alpha) + mark_channel as f32 * alpha).round() as u8;
+            }
+        }
+
+        DynamicImage::ImageRgb8(rgb)
+    }
+}
+
+// `text` in white with its shadow, `None` if the font could not be read
+fn render_text(text: &str) -> Option<RgbaImage> {
+    let mut codepoints = text.chars().map(|c| c as i32).collect::<Vec<_>>();
+    codepoints.sort_unstable();
+    codepoints.dedup();
+
+    // only the bitmaps of the glyphs are made, no texture, so a window is not needed
+    let glyphs_ptr = unsafe {
+        ffi::LoadFontData(UI_FONT_DATA.as_ptr(), UI_FONT_DATA.len() as i32, TEXT_BASE_SIZE, codepoints.as_mut_ptr(), codepoints.len() as i32, ffi::FontType::FONT_DEFAULT as i32)
+    };
+    if glyphs_ptr.is_null() {
+        return None;
+    }
+    let glyphs = unsafe { std::slice::from_raw_parts(glyphs_ptr, codepoints.len()) };
+
+    // coverage of the text, as the grayscale bitmaps of the glyphs are placed side by side
+    let spacing = TEXT_BASE_SIZE / 20;
+    let mut placed = Vec::new();
+    let mut pen = 0;
+    let mut text_height = TEXT_BASE_SIZE;
+    for c in text.chars() {
+        let glyph = match glyphs.iter().find(|glyph| glyph.value == c as i32) {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+        placed.push((glyph, pen + glyph.offsetX, glyph.offsetY));
+        text_height = text_height.max(glyph.offsetY + glyph.image.height);
+        pen += if glyph.advanceX > 0 { glyph.advanceX } else { glyph.image.width } + spacing;
+    }
+    let text_width = (pen - spacing).max(1);
+
+    let mut coverage = vec![0u8; text_width as usize * text_height as usize];
+    for (glyph, glyph_x, glyph_y) in placed {
+        let image = &glyph.image;
+        if image.data.is_null() || image.format != ffi::PixelFormat::PIXELFORMAT_UNCOMPRESSED_GRAYSCALE as i32 {
+            continue;
+        }
+        let bitmap = unsafe { std::slice::from_raw_parts(image.data as *const u8, (image.width * image.height) as usize) };
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let (text_x, text_y) = (glyph_x + x, glyph_y + y);
+                if (0..text_width).contains(&text_x) && (0..text_height).contains(&text_y) {
+                    let covered = &mut coverage[(text_y * text_width + text_x) as usize];
+                    *covered = (*covered).max(bitmap[(y * image.width + x) as usize]);
+                }
+            }
+        }
+    }
+    unsafe { ffi::UnloadFontData(glyphs_ptr, codepoints.len() as i32) };
+
+    let coverage_at = |x: i64, y: i64| {
+        if (0..text_width as i64).contains(&x) && (0..text_height as i64).contains(&y) {
+            coverage[(y * text_width as i64 + x) as usize] as f32 / 255.0
+        } else {
+            0.0
+        }
+    };
+    let size = |side: i32| side as u32 + TEXT_SHADOW_OFFSET as u32;
+    Some(RgbaImage::from_fn(size(text_width), size(text_height), |x, y| {
+        let text_alpha = coverage_at(x as i64, y as i64);
+        let shadow_alpha = coverage_at(x as i64 - TEXT_SHADOW_OFFSET, y as i64 - TEXT_SHADOW_OFFSET) * TEXT_SHADOW_ALPHA;
+        // white text over the black shadow
+        let alpha = text_alpha + shadow_alpha * (1.0 - text_alpha);
+        let white = if alpha > 0.0 { text_alpha / alpha } else { 0.0 };
+        image::Rgba([(white * 255.0) as u8, (white * 255.0) as u8, (white * 255.0) as u8, (alpha * 255.0).round() as u8])
+    }))
+}
+
+// the logo above the text, scaled to the width of the text
+fn stack(logo: &RgbaImage, text: &RgbaImage) -> RgbaImage {
+    let logo_width = text.width();
+    let logo_height = ((logo.height() as f32 * logo_width as f32 / logo.width() as f32).round() as u32).max(1);
+    let logo = imageops::resize(logo, logo_width, logo_height, FilterType::Lanczos3);
+    let gap = TEXT_BASE_SIZE as u32 / 4;
+
+    let mut mark = RgbaImage::new(text.width(), logo_height + gap + text.height());
+    imageops::replace(&mut mark, &logo, 0, 0);
+    imageops::replace(&mut mark, text, 0, (logo_height + gap) as i64);
+    mark
+}